@@ -1,4 +1,4 @@
-use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
 use rand::{Rng, seq::SliceRandom};
 use std::{collections::BTreeMap, hint::black_box};
 
@@ -197,5 +197,243 @@ fn bench_removes(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_inserts, bench_searches, bench_removes);
+fn bench_ranges(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Ranges");
+
+    // A short window in the middle: if `range` descends straight to its start, the time stays
+    // flat (logarithmic) as the tree grows instead of growing with the tree size.
+    for size in [1_000u32, 10_000, 100_000].iter() {
+        let mut rng = rand::rng();
+        let mut keys: Vec<u32> = (0..*size).collect();
+        keys.shuffle(&mut rng);
+
+        let mut rb_tree = RBTree::new();
+        let mut btree_map = BTreeMap::new();
+        for &key in keys.iter() {
+            rb_tree.insert(key, key);
+            btree_map.insert(key, key);
+        }
+
+        let mid = size / 2;
+
+        group.bench_with_input(BenchmarkId::new("RBTree", size), &mid, |b, &mid| {
+            b.iter(|| {
+                let sum: u32 = rb_tree.range(mid..mid + 10).map(|(_, v)| *v).sum();
+                black_box(sum)
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("BTreeMap", size), &mid, |b, &mid| {
+            b.iter(|| {
+                let sum: u32 = btree_map.range(mid..mid + 10).map(|(_, v)| *v).sum();
+                black_box(sum)
+            });
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_hinted_inserts(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Hinted Inserts");
+
+    // Sorted input, so every hint is the previous key and each insert lands next to it.
+    for size in [1_000u32, 10_000, 100_000].iter() {
+        let keys: Vec<u32> = (0..*size).collect();
+
+        group.bench_with_input(BenchmarkId::new("insert", size), &keys, |b, keys| {
+            b.iter(|| {
+                let mut tree = RBTree::new();
+                for &key in keys {
+                    tree.insert(key, key);
+                }
+                black_box(tree)
+            });
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("insert_with_hint", size),
+            &keys,
+            |b, keys| {
+                b.iter(|| {
+                    let mut tree = RBTree::new();
+                    let mut hint = None;
+                    for &key in keys {
+                        tree.insert_with_hint(hint.as_ref(), key, key);
+                        hint = Some(key);
+                    }
+                    black_box(tree)
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_promoted_gets(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Promoted Gets");
+    let size = 100_000;
+    let mut rng = rand::rng();
+    let mut keys: Vec<u32> = (0..size).collect();
+    keys.shuffle(&mut rng);
+
+    let mut rb_tree = RBTree::new();
+    for &key in keys.iter() {
+        rb_tree.insert(key, key);
+    }
+    let mut promoted = rb_tree.clone();
+
+    // A small hot set read over and over: `get_promote` lifts each hot key a level where it
+    // safely can, so the later reads walk shorter paths.
+    let hot: Vec<u32> = keys[..16].to_vec();
+
+    group.bench_function("get", |b| {
+        b.iter(|| {
+            for key in &hot {
+                black_box(rb_tree.get(key));
+            }
+        })
+    });
+
+    group.bench_function("get_promote", |b| {
+        b.iter(|| {
+            for key in &hot {
+                black_box(promoted.get_promote(key));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_get_or_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Get Or Insert");
+    let size = 100_000u32;
+
+    let mut rb_tree = RBTree::new();
+    for key in 0..size {
+        rb_tree.insert(key * 2, key);
+    }
+    // Odd keys all miss, so `get` + `insert` walks each path twice while `get_or_insert`
+    // walks it once.
+    let mut rng = rand::rng();
+    let mut probes: Vec<u32> = (0..1_000).map(|key| key * 2 * (size / 1_000) + 1).collect();
+    probes.shuffle(&mut rng);
+
+    group.bench_function("get + insert", |b| {
+        b.iter_batched(
+            || rb_tree.clone(),
+            |mut tree| {
+                for &key in &probes {
+                    if tree.get(&key).is_none() {
+                        tree.insert(key, 0);
+                    }
+                    black_box(tree.get_mut(&key));
+                }
+                tree
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("get_or_insert", |b| {
+        b.iter_batched(
+            || rb_tree.clone(),
+            |mut tree| {
+                for &key in &probes {
+                    black_box(tree.get_or_insert(key, 0));
+                }
+                tree
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+fn bench_bulk_builds(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Bulk Builds");
+
+    for size in [1_000u32, 10_000, 100_000].iter() {
+        let mut rng = rand::rng();
+        let mut pairs: Vec<(u32, u32)> = (0..*size).map(|key| (key, key)).collect();
+        pairs.shuffle(&mut rng);
+
+        group.bench_with_input(BenchmarkId::new("insert", size), &pairs, |b, pairs| {
+            b.iter_batched(
+                || pairs.clone(),
+                |pairs| {
+                    let mut tree = RBTree::new();
+                    for (key, value) in pairs {
+                        tree.insert(key, value);
+                    }
+                    tree
+                },
+                BatchSize::LargeInput,
+            )
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("from_unsorted", size),
+            &pairs,
+            |b, pairs| {
+                b.iter_batched(
+                    || pairs.clone(),
+                    RBTree::from_unsorted,
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_full_iteration(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Full Iteration");
+    let size = 100_000u32;
+    let mut rng = rand::rng();
+    let mut keys: Vec<u32> = (0..size).collect();
+    keys.shuffle(&mut rng);
+
+    let mut rb_tree = RBTree::new();
+    for &key in &keys {
+        rb_tree.insert(key, key);
+    }
+
+    // `range(..)` steps with `inorder_successor`, climbing parent links after each right-most
+    // leaf; `iter()` keeps the pending ancestors on a stack instead.
+    group.bench_function("successor (range(..))", |b| {
+        b.iter(|| {
+            for entry in rb_tree.range::<u32, _>(..) {
+                black_box(entry);
+            }
+        })
+    });
+
+    group.bench_function("stack (iter())", |b| {
+        b.iter(|| {
+            for entry in rb_tree.iter() {
+                black_box(entry);
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_inserts,
+    bench_searches,
+    bench_removes,
+    bench_ranges,
+    bench_hinted_inserts,
+    bench_promoted_gets,
+    bench_get_or_insert,
+    bench_bulk_builds,
+    bench_full_iteration
+);
 criterion_main!(benches);
@@ -14,9 +14,7 @@ fn main() {
         unique_key.insert(rand_i32);
     }
 
-    if let Err(e) = tree.validate() {
-        panic!("ERR: {e}");
-    }
+    tree.assert_valid();
 
     for key in &unique_key {
         let now = Instant::now();
@@ -36,9 +34,7 @@ fn main() {
         tree.remove(key);
 
         let now = Instant::now();
-        if let Err(e) = tree.validate() {
-            panic!("ERR: {e}");
-        }
+        tree.assert_valid();
         println!("Validate duration for {key}: {:?}", now.elapsed());
     }
 }
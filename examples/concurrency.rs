@@ -1,32 +1,25 @@
-use std::{
-    sync::{LazyLock, RwLock},
-    thread,
-};
+use std::{sync::LazyLock, thread};
 
-use rb_tree::RBTree;
+use rb_tree::RwRBTree;
 
-static RB_TREE: LazyLock<RwLock<RBTree<String, i32>>> =
-    LazyLock::new(|| RwLock::new(RBTree::new()));
+static RB_TREE: LazyLock<RwRBTree<String, i32>> = LazyLock::new(RwRBTree::new);
 
 fn main() {
     let t1 = thread::spawn(|| {
         for i in 0..=10 {
-            let mut tree = RB_TREE.write().unwrap();
-            tree.insert(format!("key{}", i), i);
+            RB_TREE.insert(format!("key{}", i), i);
         }
     });
 
     let t2 = thread::spawn(|| {
         for i in 11..=20 {
-            let mut tree = RB_TREE.write().unwrap();
-            tree.insert(format!("key{}", i), i);
+            RB_TREE.insert(format!("key{}", i), i);
         }
     });
 
     let t3 = thread::spawn(|| {
         for i in 21..=30 {
-            let mut tree = RB_TREE.write().unwrap();
-            tree.insert(format!("key{}", i), i);
+            RB_TREE.insert(format!("key{}", i), i);
         }
     });
 
@@ -36,22 +29,19 @@ fn main() {
 
     let read_thread_1 = thread::spawn(|| {
         for i in 0..=10 {
-            let tree = RB_TREE.read().unwrap();
-            println!("{:?}", tree.get(&format!("key{}", i)));
+            println!("{:?}", RB_TREE.get_cloned(&format!("key{}", i)));
         }
     });
 
     let read_thread_2 = thread::spawn(|| {
         for i in 11..=20 {
-            let tree = RB_TREE.read().unwrap();
-            println!("{:?}", tree.get(&format!("key{}", i)));
+            println!("{:?}", RB_TREE.get_cloned(&format!("key{}", i)));
         }
     });
 
     let read_thread_3 = thread::spawn(|| {
         for i in 21..=30 {
-            let tree = RB_TREE.read().unwrap();
-            println!("{:?}", tree.get(&format!("key{}", i)));
+            println!("{:?}", RB_TREE.get_cloned(&format!("key{}", i)));
         }
     });
 
@@ -0,0 +1,70 @@
+use rb_tree::{Augment, NodeRef, OrdComparator, RBTree};
+
+/// Keyed by `(start, end)`.
+#[derive(Debug)]
+struct Interval {
+    name: &'static str,
+    end: u32,
+}
+
+/// Summarizes each subtree by the largest `end` in it.
+struct MaxEnd;
+
+impl Augment<(u32, u32), Interval> for MaxEnd {
+    type Summary = u32;
+
+    fn summarize(
+        _key: &(u32, u32),
+        value: &Interval,
+        left: Option<&u32>,
+        right: Option<&u32>,
+    ) -> u32 {
+        [left, right]
+            .into_iter()
+            .flatten()
+            .fold(value.end, |max, &end| max.max(end))
+    }
+}
+
+/// Collects every interval overlapping `[lo, hi]`, skipping subtrees whose largest `end` is
+/// below `lo` and right subtrees that start after `hi`.
+fn overlapping<'a>(
+    node: Option<NodeRef<'a, (u32, u32), Interval, OrdComparator, MaxEnd>>,
+    lo: u32,
+    hi: u32,
+    out: &mut Vec<&'a str>,
+) {
+    let Some(node) = node else { return };
+    if *node.summary() < lo {
+        return;
+    }
+
+    overlapping(node.left(), lo, hi, out);
+    let &(start, end) = node.key();
+    if start <= hi && end >= lo {
+        out.push(node.value().name);
+    }
+    if start <= hi {
+        overlapping(node.right(), lo, hi, out);
+    }
+}
+
+fn main() {
+    let mut tree = RBTree::with_augment::<MaxEnd>();
+    for (start, end, name) in [
+        (1, 3, "breakfast"),
+        (9, 17, "work"),
+        (12, 13, "lunch"),
+        (18, 19, "dinner"),
+        (20, 23, "film"),
+        (7, 8, "commute"),
+    ] {
+        tree.insert((start, end), Interval { name, end });
+    }
+
+    for (lo, hi) in [(12, 12), (8, 10), (19, 21), (4, 6)] {
+        let mut found = vec![];
+        overlapping(tree.root(), lo, hi, &mut found);
+        println!("[{lo}, {hi}] overlaps {found:?}");
+    }
+}
@@ -0,0 +1,22 @@
+//! Builds the core map in a `#![no_std]` crate that only has `alloc`.
+//!
+//! Check it with `cargo build --no-default-features --example no_std`.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use rb_tree::RBTree;
+
+pub fn sorted_squares(values: &[u32]) -> Vec<(u32, u64)> {
+    let mut tree = RBTree::new();
+    for &v in values {
+        tree.insert(v, u64::from(v) * u64::from(v));
+    }
+
+    tree.remove(&0);
+    debug_assert!(tree.get(&1).is_none_or(|square| *square == 1));
+
+    tree.iter().map(|(k, v)| (*k, *v)).collect()
+}
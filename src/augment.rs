@@ -0,0 +1,114 @@
+use crate::{
+    Comparator, OrdComparator, RBTree,
+    node::{Key, NodePtr, Value},
+};
+
+// Augmented trees keep a summary of each subtree in its root so that queries can skip whole
+// subtrees. The tree maintains one summary itself, the subtree size behind the order statistics,
+// and an `Augment` adds another, stored in every node next to its entry. Both are recomputed by
+// `update_node`, which every structural change (insert, remove, rotation, split/join) calls
+// bottom-up at exactly the nodes whose subtrees changed.
+//
+// The size is a plain node field rather than an `Augment` of its own: the positional queries in
+// order_statistics.rs and the `len` bookkeeping of `split_off_after` work on every tree, whatever
+// `Augment` it was given, and the nil sentinel's size of 0 spares them a branch per child.
+
+/// A per-node summary kept up to date by the tree, e.g. the largest endpoint below a node for
+/// an interval tree.
+///
+/// Every node stores a `Summary` of its subtree next to its entry, computed by `summarize` from
+/// the node's own entry and the summaries of its children. Read it back with
+/// [`NodeRef::summary`](crate::NodeRef::summary). It must not touch the key's ordering.
+pub trait Augment<K, V> {
+    type Summary;
+
+    fn summarize(
+        key: &K,
+        value: &V,
+        left: Option<&Self::Summary>,
+        right: Option<&Self::Summary>,
+    ) -> Self::Summary;
+}
+
+/// The `Augment` of a tree that keeps no summary besides the built-in subtree sizes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoAugment;
+
+impl<K, V> Augment<K, V> for NoAugment {
+    type Summary = ();
+
+    fn summarize(_key: &K, _value: &V, _left: Option<&()>, _right: Option<&()>) {}
+}
+
+impl<K: Key, V: Value> RBTree<K, V> {
+    /// Creates an empty tree that maintains the summary described by `A`.
+    ///
+    /// Summaries are refreshed whenever the tree's shape changes or a value changes: `get_mut`
+    /// and the other single-value accessors return a [`ValueMut`](crate::ValueMut) that
+    /// refreshes the path above its entry when dropped. `iter_mut`, `range_mut_pair` and
+    /// `get_pair_mut` are not available on augmented trees; `map_values_in_place` edits every
+    /// value instead.
+    pub fn with_augment<A: Augment<K, V>>() -> RBTree<K, V, OrdComparator, A> {
+        RBTree::with_comparator(OrdComparator)
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Whether `A` stores anything; a zero-sized summary has nothing to refresh.
+    pub(crate) fn has_summary() -> bool {
+        size_of::<A::Summary>() != 0
+    }
+
+    /// Recomputes `node`'s subtree size and augmented summary from its children, whose own
+    /// summaries must already be up to date.
+    pub(crate) fn update_node(&self, mut node: NodePtr<K, V, A>) {
+        unsafe {
+            let node = node.as_mut();
+            node.update_size();
+
+            if Self::has_summary() {
+                let left = (!self.is_nil(node.left)).then(|| node.left.as_ref().summary());
+                let right = (!self.is_nil(node.right)).then(|| node.right.as_ref().summary());
+                let summary = A::summarize(node.key(), node.value(), left, right);
+                *node.summary.assume_init_mut() = summary;
+            }
+        }
+    }
+
+    /// Recomputes the summaries from `node` up to the root, after a node was linked into or
+    /// unlinked from `node`'s subtree.
+    pub(crate) fn update_upward(&self, mut node: NodePtr<K, V, A>) {
+        while !self.is_header(node) && !self.is_nil(node) {
+            self.update_node(node);
+            node = unsafe { node.as_ref().parent };
+        }
+    }
+
+    /// Recomputes every augmented summary bottom-up, after values all over the tree were
+    /// edited in place.
+    pub(crate) fn refresh_summaries(&self) {
+        if Self::has_summary() {
+            self.refresh_subtree(unsafe { self.header.as_ref().right });
+        }
+    }
+
+    fn refresh_subtree(&self, node: NodePtr<K, V, A>) {
+        if self.is_nil(node) {
+            return;
+        }
+
+        unsafe {
+            self.refresh_subtree(node.as_ref().left);
+            self.refresh_subtree(node.as_ref().right);
+        }
+        self.update_node(node);
+    }
+
+    /// Refreshes the augmented summaries above `node` after its value was replaced. Sizes are
+    /// unaffected, so this is a no-op without an `Augment`.
+    pub(crate) fn value_replaced(&self, node: NodePtr<K, V, A>) {
+        if Self::has_summary() {
+            self.update_upward(node);
+        }
+    }
+}
@@ -1,10 +1,12 @@
-use std::{
+use alloc::{boxed::Box, vec::Vec};
+use core::{
     borrow::Borrow,
     mem::{ManuallyDrop, MaybeUninit},
     ptr::NonNull,
 };
 
 use crate::{
+    Comparator, OrdComparator,
     binary_search_tree::{BinarySearchTree as BSTTrait, InsertResult},
     binary_tree::{BinaryTree, NodePosition},
     node::{Color, Key, NodePtr, RBNode, Value},
@@ -22,7 +24,9 @@ impl<K: Key, V: Value> BinarySearchTree<K, V> {
         let mut nil_node = Box::new(RBNode {
             key: MaybeUninit::uninit(),
             value: MaybeUninit::uninit(),
+            summary: MaybeUninit::uninit(),
             color: Color::Black,
+            size: 0,
             left: NonNull::dangling(),
             right: NonNull::dangling(),
             parent: NonNull::dangling(),
@@ -38,7 +42,9 @@ impl<K: Key, V: Value> BinarySearchTree<K, V> {
         let header_node = Box::new(RBNode {
             key: MaybeUninit::uninit(),
             value: MaybeUninit::uninit(),
+            summary: MaybeUninit::uninit(),
             color: Color::Black,
+            size: 0,
             left: leaked_nil_ptr,
             right: leaked_nil_ptr,
             parent: leaked_nil_ptr,
@@ -64,7 +70,9 @@ impl<K: Key, V: Value> BinarySearchTree<K, V> {
         let node = Box::new(RBNode {
             key: MaybeUninit::new(ManuallyDrop::new(key)),
             value: MaybeUninit::new(ManuallyDrop::new(value)),
+            summary: MaybeUninit::new(()),
             color: Color::Black, // All nodes are black in a simple BST
+            size: 0,             // Subtree sizes are not tracked in a simple BST
             left: self.nil,
             right: self.nil,
             parent: self.nil,
@@ -83,10 +91,10 @@ impl<K: Key, V: Value> BinarySearchTree<K, V> {
         }
     }
 
-    pub fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<(K, V)>
     where
         K: Borrow<Q>,
-        Q: Ord,
+        Q: Ord + ?Sized,
     {
         let node = self.bs_remove(key);
         if self.is_nil(node) {
@@ -102,18 +110,18 @@ impl<K: Key, V: Value> BinarySearchTree<K, V> {
         }
     }
 
-    pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
-        Q: Ord,
+        Q: Ord + ?Sized,
     {
         self.search(key)
     }
 
-    pub fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
     where
         K: Borrow<Q>,
-        Q: Ord,
+        Q: Ord + ?Sized,
     {
         self.search_mut(key)
     }
@@ -168,6 +176,145 @@ impl<K: Key, V: Value> BinarySearchTree<K, V> {
     }
 }
 
+/// A view into a single entry of a [`BinarySearchTree`], which is either occupied or vacant.
+///
+/// Works like the `RBTree` entry, minus the rebalancing after an insertion.
+pub enum Entry<'a, K: Key, V: Value> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+pub struct OccupiedEntry<'a, K: Key, V: Value> {
+    node: NodePtr<K, V>,
+    _tree: &'a mut BinarySearchTree<K, V>,
+}
+
+pub struct VacantEntry<'a, K: Key, V: Value> {
+    tree: &'a mut BinarySearchTree<K, V>,
+    key: K,
+    parent: NodePtr<K, V>,
+    position: NodePosition,
+}
+
+impl<K: Key, V: Value> BinarySearchTree<K, V> {
+    /// Gets the entry for `key` for in-place manipulation.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        let mut parent = self.header;
+        let mut cur = unsafe { self.header.as_ref().right };
+        let mut position = NodePosition::Right;
+
+        while !self.is_nil(cur) {
+            let k = unsafe { cur.as_ref().key() };
+            if &key == k {
+                return Entry::Occupied(OccupiedEntry {
+                    node: cur,
+                    _tree: self,
+                });
+            }
+
+            parent = cur;
+            if &key < k {
+                cur = unsafe { cur.as_ref().left };
+                position = NodePosition::Left;
+            } else {
+                cur = unsafe { cur.as_ref().right };
+                position = NodePosition::Right;
+            }
+        }
+
+        Entry::Vacant(VacantEntry {
+            tree: self,
+            key,
+            parent,
+            position,
+        })
+    }
+}
+
+impl<'a, K: Key, V: Value> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Inserts `default` if the entry is vacant, and returns a reference to the value.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Inserts the result of `f` if the entry is vacant, and returns a reference to the value.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, f: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    /// Calls `f` on the value if the entry is occupied.
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K: Key, V: Value> OccupiedEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        unsafe { self.node.as_ref().key() }
+    }
+
+    pub fn get(&self) -> &V {
+        unsafe { self.node.as_ref().value() }
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { self.node.as_mut().value_mut() }
+    }
+
+    /// Converts the entry into a reference to its value that lives as long as the tree borrow.
+    pub fn into_mut(mut self) -> &'a mut V {
+        unsafe { self.node.as_mut().value_mut() }
+    }
+
+    /// Replaces the value, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        core::mem::replace(self.get_mut(), value)
+    }
+}
+
+impl<'a, K: Key, V: Value> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Inserts `value` under the entry's key and returns a reference to it.
+    pub fn insert(self, value: V) -> &'a mut V {
+        let mut node = self.tree.new_node(self.key, value);
+        let mut parent = self.parent;
+        unsafe {
+            node.as_mut().parent = parent;
+            match self.position {
+                NodePosition::Left => parent.as_mut().left = node,
+                NodePosition::Right => parent.as_mut().right = node,
+            }
+        }
+        self.tree.len += 1;
+        unsafe { node.as_mut().value_mut() }
+    }
+}
+
+impl<K: Key, V: Value> Default for BinarySearchTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Implement BinaryTree trait
 impl<K: Key, V: Value> BinaryTree<K, V> for BinarySearchTree<K, V> {
     fn get_node_position(&self, child: NodePtr<K, V>) -> NodePosition {
@@ -352,11 +499,12 @@ impl<K: Key, V: Value> BinaryTree<K, V> for BinarySearchTree<K, V> {
 }
 
 // Implement BinarySearchTree trait
-impl<K: Key, V: Value> BSTTrait<K, V> for BinarySearchTree<K, V> {
-    fn search<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+impl<K: Key, V: Value> BSTTrait<K, V, OrdComparator> for BinarySearchTree<K, V> {
+    fn search<Q>(&self, key: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
-        Q: Ord,
+        Q: ?Sized,
+        OrdComparator: Comparator<Q>,
     {
         let mut cur: NodePtr<K, V> = unsafe { self.header.as_ref().right };
 
@@ -365,11 +513,11 @@ impl<K: Key, V: Value> BSTTrait<K, V> for BinarySearchTree<K, V> {
 
             let k = unsafe { cur_node.key() };
 
-            if key == k.borrow() {
+            if OrdComparator.compare(key, k.borrow()).is_eq() {
                 return unsafe { Some(cur_node.value.assume_init_ref()) };
             }
 
-            if key < k.borrow() {
+            if OrdComparator.compare(key, k.borrow()).is_lt() {
                 cur = cur_node.left;
             } else {
                 cur = cur_node.right;
@@ -379,10 +527,11 @@ impl<K: Key, V: Value> BSTTrait<K, V> for BinarySearchTree<K, V> {
         None
     }
 
-    fn search_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    fn search_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
     where
         K: Borrow<Q>,
-        Q: Ord,
+        Q: ?Sized,
+        OrdComparator: Comparator<Q>,
     {
         let mut cur: NodePtr<K, V> = unsafe { self.header.as_ref().right };
 
@@ -391,11 +540,11 @@ impl<K: Key, V: Value> BSTTrait<K, V> for BinarySearchTree<K, V> {
 
             let k = unsafe { cur_node.key().borrow() };
 
-            if key == k {
+            if OrdComparator.compare(key, k).is_eq() {
                 return unsafe { Some(cur.as_mut().value.assume_init_mut()) };
             }
 
-            if key < k {
+            if OrdComparator.compare(key, k).is_lt() {
                 cur = cur_node.left;
             } else {
                 cur = cur_node.right;
@@ -405,6 +554,33 @@ impl<K: Key, V: Value> BSTTrait<K, V> for BinarySearchTree<K, V> {
         None
     }
 
+    fn search_node<Q>(&self, key: &Q) -> NodePtr<K, V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        OrdComparator: Comparator<Q>,
+    {
+        let mut cur: NodePtr<K, V> = unsafe { self.header.as_ref().right };
+
+        while !self.is_nil(cur) {
+            let cur_node = unsafe { cur.as_ref() };
+
+            let k = unsafe { cur_node.key().borrow() };
+
+            if OrdComparator.compare(key, k).is_eq() {
+                return cur;
+            }
+
+            if OrdComparator.compare(key, k).is_lt() {
+                cur = cur_node.left;
+            } else {
+                cur = cur_node.right;
+            }
+        }
+
+        cur
+    }
+
     fn bs_insert(&mut self, key: K, value: V) -> InsertResult<K, V> {
         let mut parent = self.header;
         let mut cur = unsafe { self.header.as_ref().right };
@@ -417,7 +593,7 @@ impl<K: Key, V: Value> BSTTrait<K, V> for BinarySearchTree<K, V> {
 
             if &key == k {
                 // replace
-                let old_value = std::mem::replace(unsafe { cur_mut.value_mut() }, value);
+                let old_value = core::mem::replace(unsafe { cur_mut.value_mut() }, value);
 
                 return InsertResult::Old(old_value);
             }
@@ -450,51 +626,46 @@ impl<K: Key, V: Value> BSTTrait<K, V> for BinarySearchTree<K, V> {
         }
     }
 
-    fn bs_remove<Q: ?Sized>(&mut self, key: &Q) -> NodePtr<K, V>
+    fn bs_remove<Q>(&mut self, key: &Q) -> NodePtr<K, V>
     where
         K: Borrow<Q>,
-        Q: Ord,
+        Q: ?Sized,
+        OrdComparator: Comparator<Q>,
     {
-        let mut cur: NodePtr<K, V> = unsafe { self.header.as_ref().right };
-
-        while !self.is_nil(cur) {
-            let cur_mut = unsafe { cur.as_mut() };
-
-            let k = unsafe { cur_mut.key().borrow() };
-
-            if k == key {
-                let mut node_to_remove = cur;
-
-                if !self.is_nil(unsafe { node_to_remove.as_ref().left })
-                    && !self.is_nil(unsafe { node_to_remove.as_ref().right })
-                {
-                    // let the in-order predecessor replace it
-                    let mut inorder_predecessor = self.inorder_predecessor(cur);
+        let node = self.search_node(key);
+        if self.is_nil(node) {
+            return node;
+        }
 
-                    unsafe {
-                        std::mem::swap(inorder_predecessor.as_mut().key_mut(), cur_mut.key_mut());
-                        std::mem::swap(
-                            inorder_predecessor.as_mut().value_mut(),
-                            cur_mut.value_mut(),
-                        );
-                    }
+        self.bs_remove_node(node)
+    }
 
-                    node_to_remove = inorder_predecessor;
-                }
+    fn bs_remove_node(&mut self, mut node: NodePtr<K, V>) -> NodePtr<K, V> {
+        let mut node_to_remove = node;
 
-                self.remove_node_with_no_or_one_child(node_to_remove);
+        if !self.is_nil(unsafe { node.as_ref().left })
+            && !self.is_nil(unsafe { node.as_ref().right })
+        {
+            // let the in-order predecessor replace it
+            let mut inorder_predecessor = self.inorder_predecessor(node);
 
-                return node_to_remove;
+            unsafe {
+                core::mem::swap(
+                    inorder_predecessor.as_mut().key_mut(),
+                    node.as_mut().key_mut(),
+                );
+                core::mem::swap(
+                    inorder_predecessor.as_mut().value_mut(),
+                    node.as_mut().value_mut(),
+                );
             }
 
-            if key < k {
-                cur = cur_mut.left;
-            } else {
-                cur = cur_mut.right;
-            }
+            node_to_remove = inorder_predecessor;
         }
 
-        cur
+        self.remove_node_with_no_or_one_child(node_to_remove);
+
+        node_to_remove
     }
 
     fn remove_node_with_no_or_one_child(&mut self, node: NodePtr<K, V>) {
@@ -643,6 +814,31 @@ mod tests {
         assert_eq!(remaining, vec![(2, "two"), (4, "four"), (7, "seven")]);
     }
 
+    #[test]
+    fn test_entry_counts() {
+        let mut bst = BinarySearchTree::new();
+        for word in "the cat and the hat and the bat".split(' ') {
+            *bst.entry(word).or_insert(0) += 1;
+        }
+
+        assert_eq!(bst.len(), 5);
+        assert_eq!(bst.get("the"), Some(&3));
+        assert_eq!(bst.get("and"), Some(&2));
+        assert_eq!(bst.get("cat"), Some(&1));
+
+        bst.entry("hat")
+            .and_modify(|count| *count += 10)
+            .or_insert(0);
+        assert_eq!(bst.get("hat"), Some(&11));
+
+        let mut counted = Vec::new();
+        bst.traverse_kv(|k, v| counted.push((*k, *v)));
+        assert_eq!(
+            counted,
+            vec![("and", 2), ("bat", 1), ("cat", 1), ("hat", 11), ("the", 3)]
+        );
+    }
+
     #[test]
     fn test_all_nodes_are_black() {
         let mut bst = BinarySearchTree::new();
@@ -1,7 +1,7 @@
-use std::borrow::Borrow;
+use core::{borrow::Borrow, cmp::Ordering};
 
 use crate::{
-    RBTree,
+    Augment, Comparator, NoAugment, RBTree,
     binary_tree::{BinaryTree, NodePosition},
     node::{Key, NodePtr, Value},
 };
@@ -9,85 +9,115 @@ use crate::{
 pub mod binary_search_tree_impl;
 pub mod validate;
 
-pub(crate) enum InsertResult<K: Key, V: Value> {
+pub(crate) enum InsertResult<K: Key, V: Value, A: Augment<K, V> = NoAugment> {
     Old(V),
-    New(NodePtr<K, V>),
+    New(NodePtr<K, V, A>),
 }
 
-pub(crate) trait BinarySearchTree<K: Key, V: Value>: BinaryTree<K, V> {
-    fn search<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+pub(crate) trait BinarySearchTree<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V> = NoAugment>:
+    BinaryTree<K, V, A>
+{
+    fn search<Q>(&self, key: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
-        Q: Ord;
-    fn search_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+        Q: ?Sized,
+        C: Comparator<Q>;
+    fn search_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
     where
         K: Borrow<Q>,
-        Q: Ord;
-    fn bs_insert(&mut self, key: K, value: V) -> InsertResult<K, V>;
-    fn bs_remove<Q: ?Sized>(&mut self, key: &Q) -> NodePtr<K, V>
+        Q: ?Sized,
+        C: Comparator<Q>;
+    fn search_node<Q>(&self, key: &Q) -> NodePtr<K, V, A>
     where
         K: Borrow<Q>,
-        Q: Ord;
+        Q: ?Sized,
+        C: Comparator<Q>;
+    fn bs_insert(&mut self, key: K, value: V) -> InsertResult<K, V, A>;
+    fn bs_remove<Q>(&mut self, key: &Q) -> NodePtr<K, V, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>;
+    fn bs_remove_node(&mut self, node: NodePtr<K, V, A>) -> NodePtr<K, V, A>;
 
-    fn remove_node_with_no_or_one_child(&mut self, node_ptr: NodePtr<K, V>);
-    fn remove_node_with_no_child(&mut self, node_ptr: NodePtr<K, V>);
-    fn remove_node_with_one_child(&mut self, node_ptr: NodePtr<K, V>);
+    fn remove_node_with_no_or_one_child(&mut self, node_ptr: NodePtr<K, V, A>);
+    fn remove_node_with_no_child(&mut self, node_ptr: NodePtr<K, V, A>);
+    fn remove_node_with_one_child(&mut self, node_ptr: NodePtr<K, V, A>);
 }
 
-impl<K: Key, V: Value> BinarySearchTree<K, V> for RBTree<K, V> {
-    fn search<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> BinarySearchTree<K, V, C, A>
+    for RBTree<K, V, C, A>
+{
+    fn search<Q>(&self, key: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
-        Q: Ord,
+        Q: ?Sized,
+        C: Comparator<Q>,
     {
-        let mut cur: NodePtr<K, V> = unsafe { self.header.as_ref().right };
+        let mut cur: NodePtr<K, V, A> = unsafe { self.header.as_ref().right };
 
         while !self.is_nil(cur) {
             let cur_node = unsafe { cur.as_ref() };
 
             let k = unsafe { cur_node.key() };
 
-            if key == k.borrow() {
-                return unsafe { Some(cur_node.value()) };
-            }
-
-            if key < k.borrow() {
-                cur = cur_node.left;
-            } else {
-                cur = cur_node.right;
+            match self.cmp.compare(key, k.borrow()) {
+                Ordering::Equal => return unsafe { Some(cur_node.value()) },
+                Ordering::Less => cur = cur_node.left,
+                Ordering::Greater => cur = cur_node.right,
             }
         }
 
         None
     }
 
-    fn search_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    fn search_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
     where
         K: Borrow<Q>,
-        Q: Ord,
+        Q: ?Sized,
+        C: Comparator<Q>,
     {
-        let mut cur: NodePtr<K, V> = unsafe { self.header.as_ref().right };
+        let mut cur: NodePtr<K, V, A> = unsafe { self.header.as_ref().right };
 
         while !self.is_nil(cur) {
             let cur_node = unsafe { cur.as_ref() };
 
             let k = unsafe { cur_node.key().borrow() };
 
-            if key == k {
-                return unsafe { Some(cur.as_mut().value_mut()) };
+            match self.cmp.compare(key, k) {
+                Ordering::Equal => return unsafe { Some(cur.as_mut().value_mut()) },
+                Ordering::Less => cur = cur_node.left,
+                Ordering::Greater => cur = cur_node.right,
             }
+        }
 
-            if key < k {
-                cur = cur_node.left;
-            } else {
-                cur = cur_node.right;
+        None
+    }
+
+    fn search_node<Q>(&self, key: &Q) -> NodePtr<K, V, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let mut cur: NodePtr<K, V, A> = unsafe { self.header.as_ref().right };
+
+        while !self.is_nil(cur) {
+            let cur_node = unsafe { cur.as_ref() };
+
+            let k = unsafe { cur_node.key().borrow() };
+
+            match self.cmp.compare(key, k) {
+                Ordering::Equal => return cur,
+                Ordering::Less => cur = cur_node.left,
+                Ordering::Greater => cur = cur_node.right,
             }
         }
 
-        None
+        cur
     }
 
-    fn bs_insert(&mut self, key: K, value: V) -> InsertResult<K, V> {
+    fn bs_insert(&mut self, key: K, value: V) -> InsertResult<K, V, A> {
         let mut parent = self.header;
         let mut cur = unsafe { self.header.as_ref().right };
 
@@ -97,21 +127,24 @@ impl<K: Key, V: Value> BinarySearchTree<K, V> for RBTree<K, V> {
             let cur_mut = unsafe { cur.as_mut() };
             let k = unsafe { cur_mut.key() };
 
-            if &key == k {
-                // replace
-                let old_value = std::mem::replace(unsafe { cur_mut.value_mut() }, value);
+            match self.cmp.compare(&key, k) {
+                Ordering::Equal => {
+                    // replace
+                    let old_value = core::mem::replace(unsafe { cur_mut.value_mut() }, value);
+                    self.value_replaced(cur);
 
-                return InsertResult::Old(old_value);
-            }
-
-            if &key < k {
-                parent = cur;
-                cur = cur_mut.left;
-                node_position = NodePosition::Left;
-            } else {
-                parent = cur;
-                cur = cur_mut.right;
-                node_position = NodePosition::Right;
+                    return InsertResult::Old(old_value);
+                }
+                Ordering::Less => {
+                    parent = cur;
+                    cur = cur_mut.left;
+                    node_position = NodePosition::Left;
+                }
+                Ordering::Greater => {
+                    parent = cur;
+                    cur = cur_mut.right;
+                    node_position = NodePosition::Right;
+                }
             }
         }
 
@@ -127,59 +160,58 @@ impl<K: Key, V: Value> BinarySearchTree<K, V> for RBTree<K, V> {
                     parent.as_mut().right = new_node;
                 }
             }
+            self.update_upward(new_node);
 
             InsertResult::New(new_node)
         }
     }
 
-    fn bs_remove<Q: ?Sized>(&mut self, key: &Q) -> NodePtr<K, V>
+    fn bs_remove<Q>(&mut self, key: &Q) -> NodePtr<K, V, A>
     where
         K: Borrow<Q>,
-        Q: Ord,
+        Q: ?Sized,
+        C: Comparator<Q>,
     {
-        let mut cur: NodePtr<K, V> = unsafe { self.header.as_ref().right };
-
-        while !self.is_nil(cur) {
-            let cur_mut = unsafe { cur.as_mut() };
-
-            let k = unsafe { cur_mut.key().borrow() };
-
-            if k == key {
-                let mut node_to_remove = cur;
-
-                if !self.is_nil(unsafe { node_to_remove.as_ref().left })
-                    && !self.is_nil(unsafe { node_to_remove.as_ref().right })
-                {
-                    // let the in-order predecessor replace it
-                    let mut inorder_predecessor = self.inorder_predecessor(cur);
+        let node = self.search_node(key);
+        if self.is_nil(node) {
+            return node;
+        }
 
-                    unsafe {
-                        std::mem::swap(inorder_predecessor.as_mut().key_mut(), cur_mut.key_mut());
-                        std::mem::swap(
-                            inorder_predecessor.as_mut().value_mut(),
-                            cur_mut.value_mut(),
-                        );
-                    }
+        self.bs_remove_node(node)
+    }
 
-                    node_to_remove = inorder_predecessor;
-                }
+    fn bs_remove_node(&mut self, mut node: NodePtr<K, V, A>) -> NodePtr<K, V, A> {
+        // the removed node is freed by the caller, and entries may move between nodes below
+        self.last_inserted = self.nil;
+        let mut node_to_remove = node;
 
-                self.remove_node_with_no_or_one_child(node_to_remove);
+        if !self.is_nil(unsafe { node.as_ref().left })
+            && !self.is_nil(unsafe { node.as_ref().right })
+        {
+            // let the in-order predecessor replace it
+            let mut inorder_predecessor = self.inorder_predecessor(node);
 
-                return node_to_remove;
+            unsafe {
+                core::mem::swap(
+                    inorder_predecessor.as_mut().key_mut(),
+                    node.as_mut().key_mut(),
+                );
+                core::mem::swap(
+                    inorder_predecessor.as_mut().value_mut(),
+                    node.as_mut().value_mut(),
+                );
             }
 
-            if key < k {
-                cur = cur_mut.left;
-            } else {
-                cur = cur_mut.right;
-            }
+            node_to_remove = inorder_predecessor;
         }
 
-        cur
+        self.remove_node_with_no_or_one_child(node_to_remove);
+        self.update_upward(unsafe { node_to_remove.as_ref().parent });
+
+        node_to_remove
     }
 
-    fn remove_node_with_no_or_one_child(&mut self, node: NodePtr<K, V>) {
+    fn remove_node_with_no_or_one_child(&mut self, node: NodePtr<K, V, A>) {
         if !self.is_nil(node) {
             let left = unsafe { node.as_ref().left };
             let right = unsafe { node.as_ref().right };
@@ -192,7 +224,7 @@ impl<K: Key, V: Value> BinarySearchTree<K, V> for RBTree<K, V> {
         }
     }
 
-    fn remove_node_with_no_child(&mut self, node: NodePtr<K, V>) {
+    fn remove_node_with_no_child(&mut self, node: NodePtr<K, V, A>) {
         if !self.is_nil(node) {
             unsafe {
                 let mut parent = node.as_ref().parent;
@@ -204,7 +236,7 @@ impl<K: Key, V: Value> BinarySearchTree<K, V> for RBTree<K, V> {
         }
     }
 
-    fn remove_node_with_one_child(&mut self, node: NodePtr<K, V>) {
+    fn remove_node_with_one_child(&mut self, node: NodePtr<K, V, A>) {
         if !self.is_nil(node) {
             let mut parent = unsafe { node.as_ref().parent };
             let left = unsafe { node.as_ref().left };
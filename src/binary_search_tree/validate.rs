@@ -1,18 +1,22 @@
 use crate::{
-    RBTree,
+    Augment, Comparator, NoAugment, RBTree,
     node::{Key, NodePtr, Value},
 };
-use std::fmt::Debug;
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+use core::fmt::Debug;
 
 /// Validation trait for Binary Search Trees
-pub(crate) trait BSTValidator<K: Key, V: Value> {
+pub(crate) trait BSTValidator<K: Key, V: Value, A: Augment<K, V> = NoAugment> {
     /// Validates the entire BST structure and properties
     fn validate_bst(&self) -> Result<(), String>;
 
-    /// Validates BST property recursively with bounds
+    /// Validates BST property recursively with bounds, in the tree's comparator order
     fn validate_bst_recursive(
         &self,
-        node: NodePtr<K, V>,
+        node: NodePtr<K, V, A>,
         min_bound: Option<&K>,
         max_bound: Option<&K>,
     ) -> Result<(), String>;
@@ -21,7 +25,7 @@ pub(crate) trait BSTValidator<K: Key, V: Value> {
     fn validate_structure(&self) -> Result<(), String>;
 
     /// Validates that parent-child pointers are consistent
-    fn validate_parent_child_consistency(&self, node: NodePtr<K, V>) -> Result<(), String>;
+    fn validate_parent_child_consistency(&self, node: NodePtr<K, V, A>) -> Result<(), String>;
 
     /// Validates that there are no cycles in the tree
     fn validate_no_cycles(&self) -> Result<(), String>;
@@ -30,7 +34,9 @@ pub(crate) trait BSTValidator<K: Key, V: Value> {
     fn count_nodes(&self) -> usize;
 }
 
-impl<K: Key + Debug, V: Value> BSTValidator<K, V> for RBTree<K, V> {
+impl<K: Key + Debug, V: Value, C: Comparator<K>, A: Augment<K, V>> BSTValidator<K, V, A>
+    for RBTree<K, V, C, A>
+{
     fn validate_bst(&self) -> Result<(), String> {
         // First validate the basic structure
         self.validate_structure()?;
@@ -49,7 +55,7 @@ impl<K: Key + Debug, V: Value> BSTValidator<K, V> for RBTree<K, V> {
 
     fn validate_bst_recursive(
         &self,
-        node: NodePtr<K, V>,
+        node: NodePtr<K, V, A>,
         min_bound: Option<&K>,
         max_bound: Option<&K>,
     ) -> Result<(), String> {
@@ -61,22 +67,22 @@ impl<K: Key + Debug, V: Value> BSTValidator<K, V> for RBTree<K, V> {
         let key = unsafe { node_ref.key() };
 
         // Check if current node violates BST property with bounds
-        if let Some(min) = min_bound {
-            if key <= min {
-                return Err(format!(
-                    "BST violation: node key {:?} should be greater than {:?}",
-                    key, min
-                ));
-            }
+        if let Some(min) = min_bound
+            && self.cmp.compare(key, min).is_le()
+        {
+            return Err(format!(
+                "BST violation: node key {:?} should be greater than {:?}",
+                key, min
+            ));
         }
 
-        if let Some(max) = max_bound {
-            if key >= max {
-                return Err(format!(
-                    "BST violation: node key {:?} should be less than {:?}",
-                    key, max
-                ));
-            }
+        if let Some(max) = max_bound
+            && self.cmp.compare(key, max).is_ge()
+        {
+            return Err(format!(
+                "BST violation: node key {:?} should be less than {:?}",
+                key, max
+            ));
         }
 
         // Recursively validate left subtree (all values should be < current key)
@@ -108,7 +114,7 @@ impl<K: Key + Debug, V: Value> BSTValidator<K, V> for RBTree<K, V> {
         Ok(())
     }
 
-    fn validate_parent_child_consistency(&self, node: NodePtr<K, V>) -> Result<(), String> {
+    fn validate_parent_child_consistency(&self, node: NodePtr<K, V, A>) -> Result<(), String> {
         if self.is_nil(node) {
             return Ok(());
         }
@@ -119,6 +125,12 @@ impl<K: Key + Debug, V: Value> BSTValidator<K, V> for RBTree<K, V> {
         // Validate left child
         if !self.is_nil(node_ref.left) {
             let left_ref = unsafe { node_ref.left.as_ref() };
+            if self.is_nil(left_ref.parent) {
+                return Err(format!(
+                    "Orphaned node: left child of {:?} is linked but its parent is nil",
+                    key
+                ));
+            }
             if left_ref.parent != node {
                 return Err(format!(
                     "Parent-child inconsistency: left child of {:?} doesn't point back to parent",
@@ -131,6 +143,12 @@ impl<K: Key + Debug, V: Value> BSTValidator<K, V> for RBTree<K, V> {
         // Validate right child
         if !self.is_nil(node_ref.right) {
             let right_ref = unsafe { node_ref.right.as_ref() };
+            if self.is_nil(right_ref.parent) {
+                return Err(format!(
+                    "Orphaned node: right child of {:?} is linked but its parent is nil",
+                    key
+                ));
+            }
             if right_ref.parent != node {
                 return Err(format!(
                     "Parent-child inconsistency: right child of {:?} doesn't point back to parent",
@@ -144,13 +162,11 @@ impl<K: Key + Debug, V: Value> BSTValidator<K, V> for RBTree<K, V> {
     }
 
     fn validate_no_cycles(&self) -> Result<(), String> {
-        use std::collections::HashSet;
-        let mut visited = HashSet::new();
-        let mut rec_stack = HashSet::new();
-
         let root = unsafe { self.header.as_ref().right };
         if !self.is_nil(root) {
-            self.detect_cycle_util(root, &mut visited, &mut rec_stack)?;
+            // `validate_structure` has already checked the parent pointers, which a cycle reachable
+            // from the root can't satisfy, so counting here terminates.
+            self.detect_cycle_util(root, 0, self.count_nodes())?;
         }
 
         Ok(())
@@ -163,42 +179,62 @@ impl<K: Key + Debug, V: Value> BSTValidator<K, V> for RBTree<K, V> {
     }
 }
 
-impl<K: Key + Debug, V: Value> RBTree<K, V> {
-    /// Helper method to detect cycles using DFS
+impl<K: Key + Debug, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Helper method to detect cycles using DFS.
+    ///
+    /// A path from the root can't be longer than the tree has nodes, so a deeper one must have
+    /// revisited a node. Bounding the depth keeps the check allocation-free.
     fn detect_cycle_util(
         &self,
-        node: NodePtr<K, V>,
-        visited: &mut std::collections::HashSet<NodePtr<K, V>>,
-        rec_stack: &mut std::collections::HashSet<NodePtr<K, V>>,
+        node: NodePtr<K, V, A>,
+        depth: usize,
+        node_count: usize,
     ) -> Result<(), String> {
         if self.is_nil(node) {
             return Ok(());
         }
 
-        if rec_stack.contains(&node) {
+        if depth >= node_count {
             return Err("Cycle detected in tree structure".to_string());
         }
 
-        if visited.contains(&node) {
-            return Ok(());
-        }
-
-        visited.insert(node);
-        rec_stack.insert(node);
+        self.validate_parent_chain(node, depth)?;
 
         let node_ref = unsafe { node.as_ref() };
 
         // Check left child
-        self.detect_cycle_util(node_ref.left, visited, rec_stack)?;
+        self.detect_cycle_util(node_ref.left, depth + 1, node_count)?;
 
         // Check right child
-        self.detect_cycle_util(node_ref.right, visited, rec_stack)?;
+        self.detect_cycle_util(node_ref.right, depth + 1, node_count)?;
+
+        Ok(())
+    }
+
+    /// Checks that following `parent` from a node found at `depth` below the root reaches the
+    /// header within `depth + 1` steps, so no node is linked under a stale or looping parent.
+    fn validate_parent_chain(&self, node: NodePtr<K, V, A>, depth: usize) -> Result<(), String> {
+        let mut cur = node;
+        for _ in 0..=depth {
+            if self.is_nil(cur) || self.is_header(cur) {
+                break;
+            }
+            cur = unsafe { cur.as_ref().parent };
+        }
+
+        if !self.is_header(cur) {
+            let key = unsafe { node.as_ref().key() };
+            return Err(format!(
+                "Parent chain of {:?} doesn't reach the header in {} steps",
+                key,
+                depth + 1
+            ));
+        }
 
-        rec_stack.remove(&node);
         Ok(())
     }
 
-    /// Validates BST property by doing an in-order traversal
+    /// Validates BST property by doing an in-order traversal, in the tree's comparator order
     pub fn validate_inorder(&self) -> Result<(), String>
     where
         K: Clone,
@@ -215,15 +251,15 @@ impl<K: Key + Debug, V: Value> RBTree<K, V> {
             let node_ref = unsafe { node.as_ref() };
             let key = unsafe { node_ref.key() };
 
-            if let Some(ref prev) = prev_key {
-                if key <= prev {
-                    is_valid = false;
-                    error_msg = format!(
-                        "BST violation in inorder traversal: {:?} should be greater than {:?}",
-                        key, prev
-                    );
-                    return;
-                }
+            if let Some(ref prev) = prev_key
+                && self.cmp.compare(key, prev).is_le()
+            {
+                is_valid = false;
+                error_msg = format!(
+                    "BST violation in inorder traversal: {:?} should be greater than {:?}",
+                    key, prev
+                );
+                return;
             }
 
             prev_key = Some(key.clone());
@@ -377,6 +413,7 @@ mod tests {
         tree.bs_insert(1, "1");
         tree.bs_insert(2, "2");
         tree.bs_insert(3, "3");
+        #[cfg(feature = "std")]
         tree.display();
 
         if let Err(e) = tree.validate_bst() {
@@ -396,6 +433,7 @@ mod tests {
             tree.bs_insert(i, i.to_string());
         }
 
+        #[cfg(feature = "std")]
         tree.display();
 
         if let Err(e) = tree.validate_bst() {
@@ -429,4 +467,28 @@ mod tests {
         }
         assert_eq!(tree.count_nodes(), 20);
     }
+
+    #[test]
+    fn test_orphaned_node_is_caught() {
+        let tree = create_test_tree();
+        let root = unsafe { tree.header.as_ref().right };
+        let mut node_3 = unsafe { root.as_ref().left.as_ref().left };
+        assert_eq!(unsafe { node_3.as_ref().key() }, &3);
+
+        unsafe { node_3.as_mut().parent = tree.nil };
+        let err = tree.validate_bst().unwrap_err();
+        assert!(err.contains("Orphaned node"), "{}", err);
+        let err = tree.validate_no_cycles().unwrap_err();
+        assert!(err.contains("Parent chain of 3"), "{}", err);
+
+        unsafe { node_3.as_mut().parent = node_3 };
+        let err = tree.validate_bst().unwrap_err();
+        assert!(err.contains("doesn't point back"), "{}", err);
+        let err = tree.validate_no_cycles().unwrap_err();
+        assert!(err.contains("Parent chain of 3"), "{}", err);
+
+        // relink it so the tree can be dropped
+        unsafe { node_3.as_mut().parent = root.as_ref().left };
+        assert_eq!(tree.validate_bst(), Ok(()));
+    }
 }
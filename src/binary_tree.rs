@@ -1,5 +1,5 @@
 use crate::{
-    RBTree,
+    Augment, Comparator, NoAugment, RBTree,
     node::{Key, NodePtr, Value},
 };
 
@@ -9,23 +9,27 @@ pub(crate) enum NodePosition {
     Right,
 }
 
-pub(crate) trait BinaryTree<K: Key, V: Value> {
-    fn get_node_position(&self, child: NodePtr<K, V>) -> NodePosition;
-    fn get_parent_node_position(&self, parent: NodePtr<K, V>, child: NodePtr<K, V>)
-    -> NodePosition;
-    fn inorder_predecessor(&self, node: NodePtr<K, V>) -> NodePtr<K, V>;
-    fn inorder_successor(&self, node: NodePtr<K, V>) -> NodePtr<K, V>;
-    fn rotate_left(&mut self, node: NodePtr<K, V>);
-    fn rotate_right(&mut self, node: NodePtr<K, V>);
-    #[allow(dead_code)]
-    fn sibling(&self, node: NodePtr<K, V>) -> NodePtr<K, V>;
-    fn grandparent(&self, node: NodePtr<K, V>) -> NodePtr<K, V>;
-    fn uncle(&self, node: NodePtr<K, V>) -> NodePtr<K, V>;
-    fn sibling_of_nil(&self, parent: NodePtr<K, V>, node: NodePtr<K, V>) -> NodePtr<K, V>;
+pub(crate) trait BinaryTree<K: Key, V: Value, A: Augment<K, V> = NoAugment> {
+    fn get_node_position(&self, child: NodePtr<K, V, A>) -> NodePosition;
+    fn get_parent_node_position(
+        &self,
+        parent: NodePtr<K, V, A>,
+        child: NodePtr<K, V, A>,
+    ) -> NodePosition;
+    fn inorder_predecessor(&self, node: NodePtr<K, V, A>) -> NodePtr<K, V, A>;
+    fn inorder_successor(&self, node: NodePtr<K, V, A>) -> NodePtr<K, V, A>;
+    fn rotate_left(&mut self, node: NodePtr<K, V, A>);
+    fn rotate_right(&mut self, node: NodePtr<K, V, A>);
+    fn sibling(&self, node: NodePtr<K, V, A>) -> NodePtr<K, V, A>;
+    fn grandparent(&self, node: NodePtr<K, V, A>) -> NodePtr<K, V, A>;
+    fn uncle(&self, node: NodePtr<K, V, A>) -> NodePtr<K, V, A>;
+    fn sibling_of_nil(&self, parent: NodePtr<K, V, A>, node: NodePtr<K, V, A>) -> NodePtr<K, V, A>;
 }
 
-impl<K: Key, V: Value> BinaryTree<K, V> for RBTree<K, V> {
-    fn get_node_position(&self, child: NodePtr<K, V>) -> NodePosition {
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> BinaryTree<K, V, A>
+    for RBTree<K, V, C, A>
+{
+    fn get_node_position(&self, child: NodePtr<K, V, A>) -> NodePosition {
         if self.is_nil(child) {
             panic!("child cannot be nil")
         }
@@ -36,8 +40,8 @@ impl<K: Key, V: Value> BinaryTree<K, V> for RBTree<K, V> {
 
     fn get_parent_node_position(
         &self,
-        parent: NodePtr<K, V>,
-        child: NodePtr<K, V>,
+        parent: NodePtr<K, V, A>,
+        child: NodePtr<K, V, A>,
     ) -> NodePosition {
         if self.is_header(parent) {
             return NodePosition::Right;
@@ -54,7 +58,7 @@ impl<K: Key, V: Value> BinaryTree<K, V> for RBTree<K, V> {
         }
     }
 
-    fn inorder_predecessor(&self, node: NodePtr<K, V>) -> NodePtr<K, V> {
+    fn inorder_predecessor(&self, node: NodePtr<K, V, A>) -> NodePtr<K, V, A> {
         let mut cur = unsafe { node.as_ref().left };
 
         if self.is_nil(cur) {
@@ -80,7 +84,7 @@ impl<K: Key, V: Value> BinaryTree<K, V> for RBTree<K, V> {
         }
     }
 
-    fn inorder_successor(&self, node: NodePtr<K, V>) -> NodePtr<K, V> {
+    fn inorder_successor(&self, node: NodePtr<K, V, A>) -> NodePtr<K, V, A> {
         let mut cur = unsafe { node.as_ref().right };
 
         if self.is_nil(cur) {
@@ -111,7 +115,7 @@ impl<K: Key, V: Value> BinaryTree<K, V> for RBTree<K, V> {
     //     node                  right
     //       \                    /
     //       right              node
-    fn rotate_left(&mut self, mut node: NodePtr<K, V>) {
+    fn rotate_left(&mut self, mut node: NodePtr<K, V, A>) {
         unsafe {
             let mut parent = node.as_ref().parent;
 
@@ -142,7 +146,12 @@ impl<K: Key, V: Value> BinaryTree<K, V> for RBTree<K, V> {
                     right.as_mut().parent = parent;
                 }
             }
+
+            // `node` is now below `right`, so refresh it first
+            self.update_node(node);
+            self.update_node(right);
         }
+        self.rotations += 1;
     }
 
     //      parent               parent
@@ -150,7 +159,7 @@ impl<K: Key, V: Value> BinaryTree<K, V> for RBTree<K, V> {
     //     node                  left
     //      /                      \
     //    left                     node
-    fn rotate_right(&mut self, mut node: NodePtr<K, V>) {
+    fn rotate_right(&mut self, mut node: NodePtr<K, V, A>) {
         unsafe {
             let mut parent = node.as_ref().parent;
 
@@ -181,21 +190,26 @@ impl<K: Key, V: Value> BinaryTree<K, V> for RBTree<K, V> {
                     left.as_mut().parent = parent;
                 }
             }
+
+            // `node` is now below `left`, so refresh it first
+            self.update_node(node);
+            self.update_node(left);
         }
+        self.rotations += 1;
     }
 
-    fn grandparent(&self, node: NodePtr<K, V>) -> NodePtr<K, V> {
+    fn grandparent(&self, node: NodePtr<K, V, A>) -> NodePtr<K, V, A> {
         unsafe { node.as_ref().parent.as_ref().parent }
     }
 
-    fn sibling(&self, node: NodePtr<K, V>) -> NodePtr<K, V> {
+    fn sibling(&self, node: NodePtr<K, V, A>) -> NodePtr<K, V, A> {
         unsafe {
             let parent = node.as_ref().parent;
             self.sibling_of_nil(parent, node)
         }
     }
 
-    fn uncle(&self, node: NodePtr<K, V>) -> NodePtr<K, V> {
+    fn uncle(&self, node: NodePtr<K, V, A>) -> NodePtr<K, V, A> {
         unsafe {
             let parent = node.as_ref().parent;
             if self.is_header(node) || self.is_header(parent) {
@@ -211,7 +225,7 @@ impl<K: Key, V: Value> BinaryTree<K, V> for RBTree<K, V> {
         }
     }
 
-    fn sibling_of_nil(&self, parent: NodePtr<K, V>, node: NodePtr<K, V>) -> NodePtr<K, V> {
+    fn sibling_of_nil(&self, parent: NodePtr<K, V, A>, node: NodePtr<K, V, A>) -> NodePtr<K, V, A> {
         unsafe {
             if self.is_header(parent) {
                 return self.nil;
@@ -247,6 +261,7 @@ mod tests {
     #[test]
     fn test_sibling() {
         let tree = setup_tree();
+        #[cfg(feature = "std")]
         tree.display();
         let root = unsafe { tree.header.as_ref().right };
         let node_5 = unsafe { root.as_ref().left };
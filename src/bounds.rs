@@ -0,0 +1,31 @@
+use core::ops::{Bound, RangeBounds};
+
+use crate::Comparator;
+
+/// Whether `key` sorts before the start of `range` under `cmp`.
+pub(crate) fn before_start<Q, R, C>(cmp: &C, range: &R, key: &Q) -> bool
+where
+    Q: ?Sized,
+    R: RangeBounds<Q>,
+    C: Comparator<Q>,
+{
+    match range.start_bound() {
+        Bound::Included(start) => cmp.compare(key, start).is_lt(),
+        Bound::Excluded(start) => cmp.compare(key, start).is_le(),
+        Bound::Unbounded => false,
+    }
+}
+
+/// Whether `key` sorts after the end of `range` under `cmp`.
+pub(crate) fn after_end<Q, R, C>(cmp: &C, range: &R, key: &Q) -> bool
+where
+    Q: ?Sized,
+    R: RangeBounds<Q>,
+    C: Comparator<Q>,
+{
+    match range.end_bound() {
+        Bound::Included(end) => cmp.compare(key, end).is_gt(),
+        Bound::Excluded(end) => cmp.compare(key, end).is_ge(),
+        Bound::Unbounded => false,
+    }
+}
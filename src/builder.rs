@@ -0,0 +1,83 @@
+use core::fmt::Debug;
+
+use crate::{
+    Comparator, NoAugment, OrdComparator, RBTree,
+    node::{Key, Value},
+};
+
+/// Checks a tree after a mutation, see [`RBTreeBuilder::debug_validate`].
+pub(crate) type ValidateHook<K, V, C, A = NoAugment> = fn(&RBTree<K, V, C, A>);
+
+/// Configures an [`RBTree`] before it is created.
+#[derive(Debug)]
+pub struct RBTreeBuilder<K: Key, V: Value, C: Comparator<K> = OrdComparator> {
+    capacity: usize,
+    cmp: C,
+    validate_hook: Option<ValidateHook<K, V, C>>,
+}
+
+impl<K: Key, V: Value> RBTreeBuilder<K, V> {
+    /// Orders the keys with `cmp` instead of their `Ord` implementation, e.g.
+    /// `|a: &u32, b: &u32| b.cmp(a)` for a descending tree.
+    ///
+    /// Lookups by a borrowed form of the key need a comparator for that form too, see
+    /// [`Comparator`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `debug_validate` was already called: its check is tied to the comparator, so
+    /// set the comparator first.
+    pub fn comparator<D: Comparator<K>>(self, cmp: D) -> RBTreeBuilder<K, V, D> {
+        assert!(
+            self.validate_hook.is_none(),
+            "set the comparator before calling debug_validate"
+        );
+        RBTreeBuilder {
+            capacity: self.capacity,
+            cmp,
+            validate_hook: None,
+        }
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>> RBTreeBuilder<K, V, C> {
+    /// Preallocates nodes for `capacity` entries, so the first `capacity` insertions don't allocate.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Runs [`RBTree::validate`] after every call that changes the tree's links (insertions,
+    /// removals, bulk splits and joins, rebuilds) and panics on the first violation, pointing at
+    /// the operation that broke the tree. Each check walks the whole tree, and it only runs in
+    /// builds with `debug_assertions`.
+    pub fn debug_validate(mut self) -> Self
+    where
+        K: Clone + Debug,
+        V: Clone,
+    {
+        self.validate_hook = Some(|tree| {
+            if let Err(err) = tree.validate() {
+                panic!("tree invariant broken by the last mutation: {err:?}");
+            }
+        });
+        self
+    }
+
+    pub fn build(self) -> RBTree<K, V, C> {
+        let mut tree = RBTree::with_comparator(self.cmp);
+        tree.validate_hook = self.validate_hook;
+        tree.allocate_spare(self.capacity);
+        tree
+    }
+}
+
+impl<K: Key, V: Value> RBTree<K, V> {
+    pub fn builder() -> RBTreeBuilder<K, V> {
+        RBTreeBuilder {
+            capacity: 0,
+            cmp: OrdComparator,
+            validate_hook: None,
+        }
+    }
+}
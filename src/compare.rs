@@ -0,0 +1,29 @@
+use core::cmp::Ordering;
+
+/// Decides the order of the keys in an [`RBTree`](crate::RBTree).
+///
+/// A tree compares every key it stores, and every key it is asked to look up, through its
+/// comparator. `Comparator<Q>` is needed for lookups by a borrowed form `Q` of the key, so a
+/// closure comparing `&K` only serves lookups by `&K`, while [`OrdComparator`] serves any `Q`
+/// that is `Ord`.
+///
+/// The ordering must be total and must not change while the tree holds keys.
+pub trait Comparator<T: ?Sized> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+/// The default comparator, ordering keys by their `Ord` implementation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OrdComparator;
+
+impl<T: Ord + ?Sized> Comparator<T> for OrdComparator {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+impl<T: ?Sized, F: Fn(&T, &T) -> Ordering> Comparator<T> for F {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
@@ -0,0 +1,176 @@
+use core::borrow::Borrow;
+
+use crate::{
+    Augment, Comparator, NoAugment, OrdComparator, RBTree, ValueMut,
+    binary_search_tree::BinarySearchTree,
+    binary_tree::{BinaryTree, NodePosition},
+    node::{Key, NodePtr, Value},
+};
+
+/// A cursor over an [`RBTree`] that can move between entries and insert next to the current
+/// one without searching from the root.
+///
+/// Besides pointing at an entry, the cursor can point at the "ghost" position, which sits
+/// after the last entry and before the first one.
+pub struct CursorMut<
+    'a,
+    K: Key,
+    V: Value,
+    C: Comparator<K> = OrdComparator,
+    A: Augment<K, V> = NoAugment,
+> {
+    tree: &'a mut RBTree<K, V, C, A>,
+    current: NodePtr<K, V, A>,
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Returns a cursor at the first entry, or at the ghost position if the tree is empty.
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, K, V, C, A> {
+        let current = self.first_node();
+        CursorMut {
+            tree: self,
+            current,
+        }
+    }
+
+    /// Returns a cursor at the last entry, or at the ghost position if the tree is empty.
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, K, V, C, A> {
+        let current = self.last_node();
+        CursorMut {
+            tree: self,
+            current,
+        }
+    }
+
+    /// Returns a cursor at `key`, or at the ghost position if `key` is not in the tree.
+    pub fn cursor_mut<Q>(&mut self, key: &Q) -> CursorMut<'_, K, V, C, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let current = self.search_node(key);
+        CursorMut {
+            tree: self,
+            current,
+        }
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> CursorMut<'_, K, V, C, A> {
+    /// The current entry, or `None` at the ghost position.
+    #[allow(clippy::type_complexity)]
+    pub fn current(&mut self) -> Option<(&K, ValueMut<'_, K, V, C, A>)> {
+        self.tree.key_value_mut(self.current)
+    }
+
+    pub fn key(&self) -> Option<&K> {
+        if self.tree.is_nil(self.current) {
+            return None;
+        }
+        Some(unsafe { self.current.as_ref().key() })
+    }
+
+    /// Moves to the next entry. From the last entry this moves to the ghost position, and from
+    /// the ghost position to the first entry.
+    pub fn move_next(&mut self) {
+        self.current = if self.tree.is_nil(self.current) {
+            self.tree.first_node()
+        } else {
+            self.tree.inorder_successor(self.current)
+        };
+    }
+
+    /// Moves to the previous entry. From the first entry this moves to the ghost position, and
+    /// from the ghost position to the last entry.
+    pub fn move_prev(&mut self) {
+        self.current = if self.tree.is_nil(self.current) {
+            self.tree.last_node()
+        } else {
+            self.tree.inorder_predecessor(self.current)
+        };
+    }
+
+    /// Inserts an entry right after the current one (at the front if the cursor is at the
+    /// ghost position). The cursor does not move.
+    ///
+    /// `key` must sort between the current key and the next one. This is only checked in debug
+    /// builds; a misplaced key breaks lookups for the whole tree.
+    pub fn insert_after(&mut self, key: K, value: V) {
+        let next = if self.tree.is_nil(self.current) {
+            self.tree.first_node()
+        } else {
+            self.tree.inorder_successor(self.current)
+        };
+        debug_assert!(
+            self.tree.is_nil(self.current)
+                || self
+                    .tree
+                    .cmp
+                    .compare(unsafe { self.current.as_ref().key() }, &key)
+                    .is_lt(),
+            "insert_after: key must be greater than the current key"
+        );
+        debug_assert!(
+            self.tree.is_nil(next)
+                || self
+                    .tree
+                    .cmp
+                    .compare(&key, unsafe { next.as_ref().key() })
+                    .is_lt(),
+            "insert_after: key must be less than the next key"
+        );
+
+        self.splice_between(self.current, next, key, value);
+    }
+
+    /// Inserts an entry right before the current one (at the back if the cursor is at the
+    /// ghost position). The cursor does not move.
+    ///
+    /// `key` must sort between the previous key and the current one. This is only checked in
+    /// debug builds; a misplaced key breaks lookups for the whole tree.
+    pub fn insert_before(&mut self, key: K, value: V) {
+        let prev = if self.tree.is_nil(self.current) {
+            self.tree.last_node()
+        } else {
+            self.tree.inorder_predecessor(self.current)
+        };
+        debug_assert!(
+            self.tree.is_nil(self.current)
+                || self
+                    .tree
+                    .cmp
+                    .compare(&key, unsafe { self.current.as_ref().key() })
+                    .is_lt(),
+            "insert_before: key must be less than the current key"
+        );
+        debug_assert!(
+            self.tree.is_nil(prev)
+                || self
+                    .tree
+                    .cmp
+                    .compare(unsafe { prev.as_ref().key() }, &key)
+                    .is_lt(),
+            "insert_before: key must be greater than the previous key"
+        );
+
+        self.splice_between(prev, self.current, key, value);
+    }
+
+    /// Links a new node between the adjacent nodes `prev` and `next` (either may be nil).
+    fn splice_between(&mut self, prev: NodePtr<K, V, A>, next: NodePtr<K, V, A>, key: K, value: V) {
+        // Of two adjacent nodes, either `prev` has no right child or `next` has no left child,
+        // and that empty slot is exactly where the new key belongs.
+        let (parent, position) =
+            if !self.tree.is_nil(prev) && self.tree.is_nil(unsafe { prev.as_ref().right }) {
+                (prev, NodePosition::Right)
+            } else if !self.tree.is_nil(next) {
+                (next, NodePosition::Left)
+            } else {
+                // empty tree
+                (self.tree.header, NodePosition::Right)
+            };
+
+        self.tree.attach_node(parent, position, key, value);
+    }
+}
@@ -0,0 +1,261 @@
+use alloc::vec::Vec;
+
+use crate::{
+    Augment, Comparator, NoAugment, OrdComparator, RBTree, ValueMut,
+    binary_tree::NodePosition,
+    node::{Key, NodePtr, Value},
+};
+
+/// Where a missing key's node would be linked: its parent and the side below it.
+type Slot<K, V, A> = (NodePtr<K, V, A>, NodePosition);
+
+/// A view into a single entry of an [`RBTree`], which is either occupied or vacant.
+///
+/// Created by [`RBTree::entry`]. The lookup is done once; inserting through a vacant entry
+/// links the new node where the search ended instead of searching again.
+pub enum Entry<'a, K: Key, V: Value, C: Comparator<K> = OrdComparator, A: Augment<K, V> = NoAugment>
+{
+    Occupied(OccupiedEntry<'a, K, V, C, A>),
+    Vacant(VacantEntry<'a, K, V, C, A>),
+}
+
+pub struct OccupiedEntry<
+    'a,
+    K: Key,
+    V: Value,
+    C: Comparator<K> = OrdComparator,
+    A: Augment<K, V> = NoAugment,
+> {
+    tree: &'a mut RBTree<K, V, C, A>,
+    pub(crate) node: NodePtr<K, V, A>,
+}
+
+pub struct VacantEntry<
+    'a,
+    K: Key,
+    V: Value,
+    C: Comparator<K> = OrdComparator,
+    A: Augment<K, V> = NoAugment,
+> {
+    tree: &'a mut RBTree<K, V, C, A>,
+    pub(crate) key: K,
+    pub(crate) parent: NodePtr<K, V, A>,
+    pub(crate) position: NodePosition,
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Gets the entry for `key` for in-place manipulation.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, C, A> {
+        match self.find_slot(&key) {
+            Ok(node) => Entry::Occupied(OccupiedEntry { tree: self, node }),
+            Err((parent, position)) => Entry::Vacant(VacantEntry {
+                tree: self,
+                key,
+                parent,
+                position,
+            }),
+        }
+    }
+
+    /// Descends to `key`, returning its node if present, or else the parent and side where a
+    /// node for it would be linked.
+    fn find_slot(&self, key: &K) -> Result<NodePtr<K, V, A>, Slot<K, V, A>> {
+        let mut parent = self.header;
+        let mut cur = unsafe { self.header.as_ref().right };
+        let mut position = NodePosition::Right;
+
+        while !self.is_nil(cur) {
+            let ord = self.cmp.compare(key, unsafe { cur.as_ref().key() });
+            if ord.is_eq() {
+                return Ok(cur);
+            }
+
+            parent = cur;
+            if ord.is_lt() {
+                cur = unsafe { cur.as_ref().left };
+                position = NodePosition::Left;
+            } else {
+                cur = unsafe { cur.as_ref().right };
+                position = NodePosition::Right;
+            }
+        }
+
+        Err((parent, position))
+    }
+
+    /// Returns the value for `key`, inserting `default` first if the key is absent.
+    ///
+    /// This descends from the root once; checking with `get` and then calling `insert` walks
+    /// the same path twice.
+    pub fn get_or_insert(&mut self, key: K, default: V) -> ValueMut<'_, K, V, C, A> {
+        self.entry(key).or_insert(default)
+    }
+
+    /// Inserts every pair like `extend`, but hands back each replaced value together with the
+    /// key that replaced it, in the order the collisions happened.
+    pub fn insert_many<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) -> Vec<(K, V)> {
+        let mut displaced = Vec::new();
+        for (key, value) in iter {
+            match self.find_slot(&key) {
+                Ok(mut node) => {
+                    let old = core::mem::replace(unsafe { node.as_mut().value_mut() }, value);
+                    self.value_replaced(node);
+                    displaced.push((key, old));
+                }
+                Err((parent, position)) => {
+                    self.attach_node(parent, position, key, value);
+                }
+            }
+        }
+        displaced
+    }
+
+    /// Inserts `key` with `value` unless the key is already present, in which case the stored
+    /// value is kept and `value` is dropped. Returns `true` if the entry was inserted.
+    pub fn insert_or_keep(&mut self, key: K, value: V) -> bool {
+        match self.entry(key) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert_entry(value);
+                true
+            }
+        }
+    }
+
+    /// Inserts each pair whose key is absent, keeping the stored value for keys already
+    /// present (e.g. to merge defaults under user settings). Returns the number inserted.
+    pub fn extend_absent<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) -> usize {
+        let mut inserted = 0;
+        for (key, value) in iter {
+            if self.insert_or_keep(key, value) {
+                inserted += 1;
+            }
+        }
+        inserted
+    }
+}
+
+impl<'a, K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> Entry<'a, K, V, C, A> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Inserts `default` if the entry is vacant, and returns a reference to the value.
+    pub fn or_insert(self, default: V) -> ValueMut<'a, K, V, C, A> {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Inserts the result of `f` if the entry is vacant, and returns a reference to the value.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, f: F) -> ValueMut<'a, K, V, C, A> {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    /// Inserts `V::default()` if the entry is vacant, and returns a reference to the value.
+    pub fn or_default(self) -> ValueMut<'a, K, V, C, A>
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Inserts `default` if the entry is vacant, and returns the now occupied entry, e.g. to
+    /// read its key or remove it again.
+    pub fn or_insert_entry(self, default: V) -> OccupiedEntry<'a, K, V, C, A> {
+        match self {
+            Entry::Occupied(entry) => entry,
+            Entry::Vacant(entry) => entry.insert_entry(default),
+        }
+    }
+
+    /// Calls `f` on the value if the entry is occupied.
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(&mut entry.get_mut());
+        }
+        self
+    }
+
+    /// Sets the value of the entry whether or not it was occupied. Returns a reference to the
+    /// stored value and the value it replaced, which is `None` if a new node was created.
+    pub fn insert_or_replace(self, value: V) -> (ValueMut<'a, K, V, C, A>, Option<V>) {
+        match self {
+            Entry::Occupied(entry) => {
+                // the guard refreshes the summaries once it is dropped
+                let mut stored = entry.into_mut();
+                let old = core::mem::replace(&mut *stored, value);
+                (stored, Some(old))
+            }
+            Entry::Vacant(entry) => (entry.insert(value), None),
+        }
+    }
+}
+
+impl<'a, K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> OccupiedEntry<'a, K, V, C, A> {
+    pub fn key(&self) -> &K {
+        unsafe { self.node.as_ref().key() }
+    }
+
+    pub fn get(&self) -> &V {
+        unsafe { self.node.as_ref().value() }
+    }
+
+    pub fn get_mut(&mut self) -> ValueMut<'_, K, V, C, A> {
+        ValueMut::new(self.tree, self.node)
+    }
+
+    /// Converts the entry into a reference to its value that lives as long as the tree borrow.
+    pub fn into_mut(self) -> ValueMut<'a, K, V, C, A> {
+        ValueMut::new(self.tree, self.node)
+    }
+
+    /// Replaces the value, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        let old = core::mem::replace(unsafe { self.node.as_mut().value_mut() }, value);
+        self.tree.value_replaced(self.node);
+        old
+    }
+
+    /// Removes the entry from the tree, returning its value.
+    pub fn remove(self) -> V {
+        self.tree.remove_node(self.node).1
+    }
+}
+
+impl<'a, K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> VacantEntry<'a, K, V, C, A> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Gives back ownership of the key.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Inserts `value` under the entry's key and returns a reference to it.
+    pub fn insert(self, value: V) -> ValueMut<'a, K, V, C, A> {
+        let node = self
+            .tree
+            .attach_node(self.parent, self.position, self.key, value);
+        ValueMut::new(self.tree, node)
+    }
+
+    /// Inserts `value` under the entry's key and returns the occupied entry for it.
+    pub fn insert_entry(self, value: V) -> OccupiedEntry<'a, K, V, C, A> {
+        let node = self
+            .tree
+            .attach_node(self.parent, self.position, self.key, value);
+        OccupiedEntry {
+            tree: self.tree,
+            node,
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use core::hash::{Hash, Hasher};
+
+use crate::{
+    Augment, Comparator, RBTree,
+    node::{Key, Value},
+};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher` its output is fixed, so digests can be compared across
+/// processes and builds.
+struct Fnv1a(u64);
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl<K: Key + Hash, V: Value + Hash, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Returns a stable 64-bit digest of the entries. It only depends on the contents, not on the
+    /// insertion order or the tree's shape, and is reproducible across processes.
+    ///
+    /// The digest is only as stable as the `Hash` impls of `K` and `V`: std does not promise
+    /// that its `Hash` output stays the same across Rust versions or platforms (e.g. `usize`
+    /// hashes at its native width), so keep the toolchain and target fixed when comparing
+    /// digests from different builds.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = Fnv1a(FNV_OFFSET_BASIS);
+        // a fixed width, so 32- and 64-bit builds agree
+        (self.len as u64).hash(&mut hasher);
+        for (key, value) in self.iter() {
+            key.hash(&mut hasher);
+            value.hash(&mut hasher);
+        }
+
+        hasher.finish()
+    }
+}
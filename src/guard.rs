@@ -0,0 +1,209 @@
+use core::{
+    borrow::Borrow,
+    fmt::Debug,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{
+    Augment, Comparator, NoAugment, OrdComparator, RBTree,
+    binary_search_tree::BinarySearchTree,
+    node::{Key, NodePtr, Value},
+};
+
+/// Mutable access to one value, returned by `get_mut`, `OccupiedEntry::into_mut` and the other
+/// methods that hand out a single value.
+///
+/// Dropping it refreshes the [`Augment`] summaries above the entry, so a value edited in place
+/// never leaves them stale. Without an `Augment` there is nothing to refresh, and
+/// [`ValueMut::into_mut`] gives back a plain `&mut V`.
+pub struct ValueMut<
+    'a,
+    K: Key,
+    V: Value,
+    C: Comparator<K> = OrdComparator,
+    A: Augment<K, V> = NoAugment,
+> {
+    tree: &'a RBTree<K, V, C, A>,
+    node: NodePtr<K, V, A>,
+    marker: PhantomData<&'a mut V>,
+}
+
+impl<'a, K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> ValueMut<'a, K, V, C, A> {
+    /// `node` must be an entry of `tree`, which the caller keeps mutably borrowed for `'a`.
+    pub(crate) fn new(tree: &'a RBTree<K, V, C, A>, node: NodePtr<K, V, A>) -> Self {
+        ValueMut {
+            tree,
+            node,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// The key and guarded value of `node`, or `None` if it is nil.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn key_value_mut(
+        &mut self,
+        node: NodePtr<K, V, A>,
+    ) -> Option<(&K, ValueMut<'_, K, V, C, A>)> {
+        if self.is_nil(node) {
+            return None;
+        }
+
+        Some((unsafe { node.as_ref().key() }, ValueMut::new(self, node)))
+    }
+}
+
+impl<'a, K: Key, V: Value, C: Comparator<K>> ValueMut<'a, K, V, C, NoAugment> {
+    /// Converts the guard into a reference that lives as long as the tree borrow.
+    pub fn into_mut(self) -> &'a mut V {
+        let mut node = self.node;
+        core::mem::forget(self);
+        unsafe { node.as_mut().value_mut() }
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> Deref for ValueMut<'_, K, V, C, A> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        unsafe { self.node.as_ref().value() }
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> DerefMut for ValueMut<'_, K, V, C, A> {
+    fn deref_mut(&mut self) -> &mut V {
+        unsafe { self.node.as_mut().value_mut() }
+    }
+}
+
+impl<K: Key, V: Value + Debug, C: Comparator<K>, A: Augment<K, V>> Debug
+    for ValueMut<'_, K, V, C, A>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> Drop for ValueMut<'_, K, V, C, A> {
+    fn drop(&mut self) {
+        self.tree.value_replaced(self.node);
+    }
+}
+
+/// Mutable access to one value, returned by [`RBTree::get_mut_guarded`].
+///
+/// Like [`ValueMut`], dropping the guard refreshes the [`Augment`] summaries above the entry.
+/// In debug builds it then runs `validate()` and panics if the tree is no longer a valid
+/// red-black tree, catching a key or link corrupted while the value was borrowed.
+pub struct ValueGuard<
+    'a,
+    K: Key + Clone + Debug,
+    V: Value + Clone,
+    C: Comparator<K> = OrdComparator,
+    A: Augment<K, V> = NoAugment,
+> {
+    tree: &'a mut RBTree<K, V, C, A>,
+    node: NodePtr<K, V, A>,
+}
+
+impl<K: Key + Clone + Debug, V: Value + Clone, C: Comparator<K>, A: Augment<K, V>>
+    RBTree<K, V, C, A>
+{
+    /// Like `get_mut`, but returns a [`ValueGuard`] that also re-validates the tree on drop in
+    /// debug builds.
+    pub fn get_mut_guarded<Q>(&mut self, key: &Q) -> Option<ValueGuard<'_, K, V, C, A>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let node = self.search_node(key);
+        if self.is_nil(node) {
+            return None;
+        }
+
+        Some(ValueGuard { tree: self, node })
+    }
+}
+
+impl<K: Key + Clone + Debug, V: Value + Clone, C: Comparator<K>, A: Augment<K, V>> Deref
+    for ValueGuard<'_, K, V, C, A>
+{
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        unsafe { self.node.as_ref().value() }
+    }
+}
+
+impl<K: Key + Clone + Debug, V: Value + Clone, C: Comparator<K>, A: Augment<K, V>> DerefMut
+    for ValueGuard<'_, K, V, C, A>
+{
+    fn deref_mut(&mut self) -> &mut V {
+        unsafe { self.node.as_mut().value_mut() }
+    }
+}
+
+impl<K: Key + Clone + Debug, V: Value + Clone, C: Comparator<K>, A: Augment<K, V>> Drop
+    for ValueGuard<'_, K, V, C, A>
+{
+    fn drop(&mut self) {
+        self.tree.value_replaced(self.node);
+
+        #[cfg(debug_assertions)]
+        if let Err(e) = self.tree.validate() {
+            panic!("tree invalid after get_mut_guarded: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{RBTree, node::Color};
+
+    fn setup_tree() -> RBTree<i32, String> {
+        let mut tree = RBTree::new();
+        for i in 0..20 {
+            tree.insert(i, i.to_string());
+        }
+        tree
+    }
+
+    #[test]
+    fn test_guard_allows_value_edits() {
+        let mut tree = setup_tree();
+        {
+            let mut value = tree.get_mut_guarded(&7).unwrap();
+            value.push('!');
+            assert_eq!(*value, "7!");
+        }
+        assert_eq!(tree.get(&7).map(String::as_str), Some("7!"));
+        assert!(tree.get_mut_guarded(&20).is_none());
+    }
+
+    #[test]
+    fn test_guard_refreshes_summaries() {
+        let mut tree = RBTree::with_range_sum();
+        for i in 0..20 {
+            tree.insert(i, i);
+        }
+
+        *tree.get_mut_guarded(&7).unwrap() += 100;
+        assert_eq!(tree.range_sum(..), 290);
+        assert_eq!(tree.range_sum(5..10), 135);
+        assert!(tree.validate().is_ok());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "tree invalid after get_mut_guarded")]
+    fn test_guard_catches_corruption() {
+        let mut tree = setup_tree();
+        let mut root = unsafe { tree.header.as_ref().right };
+
+        let _value = tree.get_mut_guarded(&7).unwrap();
+        unsafe { root.as_mut().color = Color::Red };
+    }
+}
@@ -1,30 +1,50 @@
-use std::mem::ManuallyDrop;
+use alloc::{boxed::Box, collections::BTreeMap, vec, vec::Vec};
+use core::{
+    borrow::Borrow,
+    cmp::{Ordering, Reverse},
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    ops::RangeBounds,
+};
 
 use crate::{
-    RBTree,
+    Augment, Comparator, Entry, NoAugment, OrdComparator, RBTree,
     binary_tree::BinaryTree,
+    bounds,
     node::{Key, NodePtr, Value},
 };
 
-pub struct RBTreeIntoIter<K: Key, V: Value> {
-    ptr: NodePtr<K, V>,
-    rb_tree: ManuallyDrop<RBTree<K, V>>,
+pub struct RBTreeIntoIter<
+    K: Key,
+    V: Value,
+    C: Comparator<K> = OrdComparator,
+    A: Augment<K, V> = NoAugment,
+> {
+    ptr: NodePtr<K, V, A>,
+    rb_tree: ManuallyDrop<RBTree<K, V, C, A>>,
+    /// Walk from the largest key down instead of from the smallest up
+    descending: bool,
 }
 
-impl<K: Key, V: Value> Iterator for RBTreeIntoIter<K, V> {
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> Iterator for RBTreeIntoIter<K, V, C, A> {
     type Item = (K, V);
     fn next(&mut self) -> Option<Self::Item> {
         if self.rb_tree.is_nil(self.ptr) {
             return None;
         }
 
-        let next = self.rb_tree.inorder_successor(self.ptr);
+        let next = if self.descending {
+            self.rb_tree.inorder_predecessor(self.ptr)
+        } else {
+            self.rb_tree.inorder_successor(self.ptr)
+        };
 
         unsafe {
-            let key_wrapper = std::ptr::read(self.ptr.as_ref().key.assume_init_ref());
-            let value_wrapper = std::ptr::read(self.ptr.as_ref().value.assume_init_ref());
+            let key_wrapper = core::ptr::read(self.ptr.as_ref().key.assume_init_ref());
+            let value_wrapper = core::ptr::read(self.ptr.as_ref().value.assume_init_ref());
             let key = ManuallyDrop::into_inner(key_wrapper);
             let value = ManuallyDrop::into_inner(value_wrapper);
+            (*self.ptr.as_ptr()).summary.assume_init_drop();
 
             self.ptr = next;
             Some((key, value))
@@ -32,7 +52,7 @@ impl<K: Key, V: Value> Iterator for RBTreeIntoIter<K, V> {
     }
 }
 
-impl<K: Key, V: Value> Drop for RBTreeIntoIter<K, V> {
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> Drop for RBTreeIntoIter<K, V, C, A> {
     fn drop(&mut self) {
         // Use a loop to consume all (K V)
         for _ in &mut *self {}
@@ -50,6 +70,13 @@ impl<K: Key, V: Value> Drop for RBTreeIntoIter<K, V> {
             }
         }
 
+        // `rb_tree` is never dropped, so take the spare pool (and its buffer) out of it
+        for node_ptr in core::mem::take(&mut self.rb_tree.spare) {
+            unsafe {
+                drop(Box::from_raw(node_ptr.as_ptr()));
+            }
+        }
+
         unsafe {
             drop(Box::from_raw(self.rb_tree.header.as_ptr()));
             drop(Box::from_raw(self.rb_tree.nil.as_ptr()));
@@ -57,49 +84,307 @@ impl<K: Key, V: Value> Drop for RBTreeIntoIter<K, V> {
     }
 }
 
-impl<K: Key, V: Value> IntoIterator for RBTree<K, V> {
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> IntoIterator for RBTree<K, V, C, A> {
     type Item = (K, V);
-    type IntoIter = RBTreeIntoIter<K, V>;
+    type IntoIter = RBTreeIntoIter<K, V, C, A>;
     fn into_iter(self) -> Self::IntoIter {
         let first = self.inorder_successor(self.header);
 
         RBTreeIntoIter {
             ptr: first,
             rb_tree: ManuallyDrop::new(self),
+            descending: false,
+        }
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Consumes the tree, yielding the entries from the largest key to the smallest. Dropping
+    /// the iterator early frees the remaining entries, like `into_iter`.
+    pub fn into_iter_rev(self) -> RBTreeIntoIter<K, V, C, A> {
+        let last = self.last_node();
+
+        RBTreeIntoIter {
+            ptr: last,
+            rb_tree: ManuallyDrop::new(self),
+            descending: true,
         }
     }
 }
 
-pub struct RBTreeIter<'a, K: Key, V: Value> {
-    ptr: NodePtr<K, V>,
-    rb_tree_ref: &'a RBTree<K, V>,
+impl<K: Key, V: Value> RBTree<K, V> {
+    /// Consumes the tree into one keyed by `Reverse<K>`, whose iteration runs from the largest
+    /// original key to the smallest. The entries already arrive in the new order, so the tree
+    /// is rebuilt balanced in O(n). An `Augment` isn't carried over, since its key type differs.
+    pub fn into_reversed(self) -> RBTree<Reverse<K>, V> {
+        RBTree::from_sorted_iter(
+            self.into_iter_rev()
+                .map(|(key, value)| (Reverse(key), value)),
+        )
+    }
 }
 
-impl<'a, K: Key, V: Value> Iterator for RBTreeIter<'a, K, V> {
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Consumes the tree into a `Vec` of its entries in ascending key order. This is one O(n)
+    /// in-order walk that frees each node as its entry is moved out, with none of the
+    /// rebalancing repeated `pop_first` calls would do.
+    pub fn drain_sorted(mut self) -> Vec<(K, V)> {
+        let mut entries = Vec::with_capacity(self.len);
+        let mut stack = NodeStack::new(self.nil);
+        let mut node = unsafe { self.header.as_ref().right };
+
+        // detach the nodes up front, so the tree drops as empty whatever happens below
+        unsafe { self.header.as_mut().right = self.nil };
+        self.len = 0;
+        self.last_inserted = self.nil;
+
+        loop {
+            while !self.is_nil(node) {
+                stack.push(node);
+                node = unsafe { node.as_ref().left };
+            }
+            let Some(next) = stack.pop() else { break };
+            // its left subtree is gone already and its right one is read here, so the node
+            // itself can be freed
+            node = unsafe { next.as_ref().right };
+            entries.push(unsafe { Self::take_entry(next) });
+        }
+        entries
+    }
+
+    /// Consumes the tree into a `BTreeMap`. The entries arrive in sorted order, which
+    /// `BTreeMap`'s `FromIterator` builds from in linear time.
+    pub fn into_btree_map(self) -> BTreeMap<K, V> {
+        self.into_iter().collect()
+    }
+}
+
+/// The most nodes a root-to-leaf path can hold: a red-black tree with `n` entries is at most
+/// `2 * log2(n + 1)` deep, and `n` fits in a `usize`.
+const MAX_DEPTH: usize = 2 * usize::BITS as usize;
+
+/// A fixed-size stack of nodes for the in-order walks, so that iterating never allocates.
+struct NodeStack<K: Key, V: Value, A: Augment<K, V>> {
+    nodes: [NodePtr<K, V, A>; MAX_DEPTH],
+    len: usize,
+}
+
+impl<K: Key, V: Value, A: Augment<K, V>> NodeStack<K, V, A> {
+    /// `fill` only stands in for the unused slots and is never read.
+    fn new(fill: NodePtr<K, V, A>) -> Self {
+        NodeStack {
+            nodes: [fill; MAX_DEPTH],
+            len: 0,
+        }
+    }
+
+    /// Panics if the stack is full, which only links corrupted into a cycle can cause.
+    fn push(&mut self, node: NodePtr<K, V, A>) {
+        self.nodes[self.len] = node;
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<NodePtr<K, V, A>> {
+        self.len = self.len.checked_sub(1)?;
+        Some(self.nodes[self.len])
+    }
+
+    fn last(&self) -> Option<NodePtr<K, V, A>> {
+        self.len.checked_sub(1).map(|top| self.nodes[top])
+    }
+}
+
+pub struct RBTreeIter<
+    'a,
+    K: Key,
+    V: Value,
+    C: Comparator<K> = OrdComparator,
+    A: Augment<K, V> = NoAugment,
+> {
+    /// Nodes still to be yielded whose right subtrees haven't been entered, next on top. Each
+    /// node is pushed and popped once, so a full walk is O(n) with no climbing back up through
+    /// parent links.
+    stack: NodeStack<K, V, A>,
+    rb_tree_ref: &'a RBTree<K, V, C, A>,
+}
+
+impl<'a, K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTreeIter<'a, K, V, C, A> {
+    fn new(rb_tree_ref: &'a RBTree<K, V, C, A>) -> Self {
+        let mut iter = RBTreeIter {
+            stack: NodeStack::new(rb_tree_ref.nil),
+            rb_tree_ref,
+        };
+        iter.push_left_spine(unsafe { rb_tree_ref.header.as_ref().right });
+        iter
+    }
+
+    fn push_left_spine(&mut self, mut node: NodePtr<K, V, A>) {
+        while !self.rb_tree_ref.is_nil(node) {
+            self.stack.push(node);
+            node = unsafe { node.as_ref().left };
+        }
+    }
+}
+
+impl<'a, K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> Iterator
+    for RBTreeIter<'a, K, V, C, A>
+{
     type Item = (&'a K, &'a V);
     fn next(&mut self) -> Option<Self::Item> {
-        if self.rb_tree_ref.is_nil(self.ptr) {
+        let node = self.stack.pop()?;
+        self.push_left_spine(unsafe { node.as_ref().right });
+
+        unsafe {
+            let key = node.as_ref().key();
+            let value = node.as_ref().value();
+
+            // keys come out strictly increasing; a broken child link (e.g. after a bad
+            // rotation) fails loudly in debug builds instead of yielding a silently wrong order
+            debug_assert!(
+                self.stack.last().is_none_or(|next| self
+                    .rb_tree_ref
+                    .cmp
+                    .compare(next.as_ref().key(), key)
+                    .is_gt()),
+                "RBTreeIter yielded keys out of order"
+            );
+
+            Some((key, value))
+        }
+    }
+}
+
+pub struct RBTreeRange<
+    'a,
+    K: Key,
+    V: Value,
+    C: Comparator<K> = OrdComparator,
+    A: Augment<K, V> = NoAugment,
+> {
+    /// Next node from the front and next node from the back; both nil once exhausted
+    front: NodePtr<K, V, A>,
+    back: NodePtr<K, V, A>,
+    rb_tree_ref: &'a RBTree<K, V, C, A>,
+}
+
+impl<'a, K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> Iterator
+    for RBTreeRange<'a, K, V, C, A>
+{
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rb_tree_ref.is_nil(self.front) {
             return None;
         }
 
-        let next = self.rb_tree_ref.inorder_successor(self.ptr);
+        let node = self.front;
+        if node == self.back {
+            self.front = self.rb_tree_ref.nil;
+            self.back = self.rb_tree_ref.nil;
+        } else {
+            self.front = self.rb_tree_ref.inorder_successor(node);
+        }
+
+        unsafe { Some((node.as_ref().key(), node.as_ref().value())) }
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> DoubleEndedIterator
+    for RBTreeRange<'_, K, V, C, A>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.rb_tree_ref.is_nil(self.back) {
+            return None;
+        }
+
+        let node = self.back;
+        if node == self.front {
+            self.front = self.rb_tree_ref.nil;
+            self.back = self.rb_tree_ref.nil;
+        } else {
+            self.back = self.rb_tree_ref.inorder_predecessor(node);
+        }
+
+        unsafe { Some((node.as_ref().key(), node.as_ref().value())) }
+    }
+}
+
+/// Mutable counterpart of [`RBTreeRange`], created by [`RBTree::range_mut_pair`]. It only
+/// borrows the tree shared so that two of them can coexist; the `&'a mut V` marker keeps the
+/// tree mutably borrowed for as long as either is alive.
+pub struct RBTreeRangeMut<
+    'a,
+    K: Key,
+    V: Value,
+    C: Comparator<K> = OrdComparator,
+    A: Augment<K, V> = NoAugment,
+> {
+    front: NodePtr<K, V, A>,
+    back: NodePtr<K, V, A>,
+    rb_tree_ref: &'a RBTree<K, V, C, A>,
+    marker: PhantomData<&'a mut V>,
+}
+
+impl<'a, K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> Iterator
+    for RBTreeRangeMut<'a, K, V, C, A>
+{
+    type Item = (&'a K, &'a mut V);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rb_tree_ref.is_nil(self.front) {
+            return None;
+        }
+
+        let mut node = self.front;
+        if node == self.back {
+            self.front = self.rb_tree_ref.nil;
+            self.back = self.rb_tree_ref.nil;
+        } else {
+            self.front = self.rb_tree_ref.inorder_successor(node);
+        }
 
         unsafe {
-            let key = self.ptr.as_ref().key();
-            let value = self.ptr.as_ref().value();
+            let node = node.as_mut();
+            Some((node.key.assume_init_ref(), node.value.assume_init_mut()))
+        }
+    }
+}
 
-            self.ptr = next;
-            Some((key, value))
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> DoubleEndedIterator
+    for RBTreeRangeMut<'_, K, V, C, A>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.rb_tree_ref.is_nil(self.back) {
+            return None;
+        }
+
+        let mut node = self.back;
+        if node == self.front {
+            self.front = self.rb_tree_ref.nil;
+            self.back = self.rb_tree_ref.nil;
+        } else {
+            self.back = self.rb_tree_ref.inorder_predecessor(node);
+        }
+
+        unsafe {
+            let node = node.as_mut();
+            Some((node.key.assume_init_ref(), node.value.assume_init_mut()))
         }
     }
 }
 
-pub struct RBTreeIterMut<'a, K: Key, V: Value> {
-    ptr: NodePtr<K, V>,
-    rb_tree_mut: &'a mut RBTree<K, V>,
+pub struct RBTreeIterMut<
+    'a,
+    K: Key,
+    V: Value,
+    C: Comparator<K> = OrdComparator,
+    A: Augment<K, V> = NoAugment,
+> {
+    ptr: NodePtr<K, V, A>,
+    rb_tree_mut: &'a mut RBTree<K, V, C, A>,
 }
 
-impl<'a, K: Key, V: Value> Iterator for RBTreeIterMut<'a, K, V> {
+impl<'a, K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> Iterator
+    for RBTreeIterMut<'a, K, V, C, A>
+{
     type Item = (&'a K, &'a mut V);
     fn next(&mut self) -> Option<Self::Item> {
         if self.rb_tree_mut.is_nil(self.ptr) {
@@ -118,23 +403,20 @@ impl<'a, K: Key, V: Value> Iterator for RBTreeIterMut<'a, K, V> {
     }
 }
 
-impl<'a, K: Key, V: Value> IntoIterator for &'a RBTree<K, V> {
+impl<'a, K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> IntoIterator
+    for &'a RBTree<K, V, C, A>
+{
     type Item = (&'a K, &'a V);
-    type IntoIter = RBTreeIter<'a, K, V>;
+    type IntoIter = RBTreeIter<'a, K, V, C, A>;
 
     fn into_iter(self) -> Self::IntoIter {
-        let first = self.inorder_successor(self.header);
-
-        RBTreeIter {
-            ptr: first,
-            rb_tree_ref: self,
-        }
+        RBTreeIter::new(self)
     }
 }
 
-impl<'a, K: Key, V: Value> IntoIterator for &'a mut RBTree<K, V> {
+impl<'a, K: Key, V: Value, C: Comparator<K>> IntoIterator for &'a mut RBTree<K, V, C, NoAugment> {
     type Item = (&'a K, &'a mut V);
-    type IntoIter = RBTreeIterMut<'a, K, V>;
+    type IntoIter = RBTreeIterMut<'a, K, V, C>;
 
     fn into_iter(self) -> Self::IntoIter {
         let first = self.inorder_successor(self.header);
@@ -146,17 +428,167 @@ impl<'a, K: Key, V: Value> IntoIterator for &'a mut RBTree<K, V> {
     }
 }
 
-impl<K: Key, V: Value> RBTree<K, V> {
-    pub fn iter(&self) -> RBTreeIter<'_, K, V> {
-        let first = self.inorder_successor(self.header);
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Iterates over the entries in ascending key order. Debug builds assert that every key
+    /// is strictly greater than the one before it.
+    pub fn iter(&self) -> RBTreeIter<'_, K, V, C, A> {
+        RBTreeIter::new(self)
+    }
 
-        RBTreeIter {
-            ptr: first,
+    /// Iterates over the keys in ascending order. `keys_range(..)` can also walk them from
+    /// the back.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Iterates over the values in ascending key order.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// Iterates over each pair of adjacent entries in ascending order, e.g. to compute deltas
+    /// along a sorted series. Yields nothing for fewer than two entries.
+    pub fn pairs(&self) -> impl Iterator<Item = ((&K, &V), (&K, &V))> {
+        self.iter().zip(self.iter().skip(1))
+    }
+
+    /// Splits the entries, in ascending order, into runs whose keys map to the same group
+    /// under `f`, e.g. bucketing timestamps by hour. `f` is called once per key, and only
+    /// adjacent keys are grouped, so `f` should be monotonic for each group to be one run.
+    pub fn group_by<G: PartialEq, F: FnMut(&K) -> G>(
+        &self,
+        mut f: F,
+    ) -> impl Iterator<Item = Vec<(&K, &V)>> {
+        let mut iter = self.iter();
+        // first entry of the next run, with its group
+        let mut pending = iter.next().map(|entry| (f(entry.0), entry));
+
+        core::iter::from_fn(move || {
+            let (group, first) = pending.take()?;
+            let mut run = vec![first];
+            for entry in iter.by_ref() {
+                let next_group = f(entry.0);
+                if next_group != group {
+                    pending = Some((next_group, entry));
+                    break;
+                }
+                run.push(entry);
+            }
+            Some(run)
+        })
+    }
+
+    /// Iterates in ascending order starting at the first key `>= key`. Handy for resuming a
+    /// paginated scan from the next key of the previous page.
+    pub fn iter_from<Q>(&self, key: &Q) -> RBTreeIter<'_, K, V, C, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        // keep the nodes where the search for `key` turned left: they and their right
+        // subtrees are exactly the keys `>= key`, smallest on top
+        let mut iter = RBTreeIter {
+            stack: NodeStack::new(self.nil),
             rb_tree_ref: self,
+        };
+        let mut cur = unsafe { self.header.as_ref().right };
+        while !self.is_nil(cur) {
+            if self
+                .cmp
+                .compare(unsafe { cur.as_ref().key() }.borrow(), key)
+                .is_lt()
+            {
+                cur = unsafe { cur.as_ref().right };
+            } else {
+                iter.stack.push(cur);
+                cur = unsafe { cur.as_ref().left };
+            }
+        }
+        iter
+    }
+
+    /// Iterates in ascending order over the entries whose keys lie in `range`. Both ends are
+    /// found with one descent each, so creating the iterator is O(log n).
+    pub fn range<Q, R>(&self, range: R) -> RBTreeRange<'_, K, V, C, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        R: RangeBounds<Q>,
+    {
+        let (front, back) = self.range_ends(&range);
+        RBTreeRange {
+            front,
+            back,
+            rb_tree_ref: self,
+        }
+    }
+
+    /// Iterates over just the keys in `range`, in ascending order.
+    pub fn keys_range<Q, R>(&self, range: R) -> impl DoubleEndedIterator<Item = &K>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        R: RangeBounds<Q>,
+    {
+        self.range(range).map(|(key, _)| key)
+    }
+
+    /// Finds the first and last nodes in `range`, with one descent each. Both are nil when no
+    /// key is in range.
+    fn range_ends<Q, R>(&self, range: &R) -> (NodePtr<K, V, A>, NodePtr<K, V, A>)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        R: RangeBounds<Q>,
+    {
+        let mut front = self.nil;
+        let mut cur = unsafe { self.header.as_ref().right };
+        while !self.is_nil(cur) {
+            if bounds::before_start(&self.cmp, range, unsafe { cur.as_ref().key() }.borrow()) {
+                cur = unsafe { cur.as_ref().right };
+            } else {
+                front = cur;
+                cur = unsafe { cur.as_ref().left };
+            }
         }
+
+        let mut back = self.nil;
+        let mut cur = unsafe { self.header.as_ref().right };
+        while !self.is_nil(cur) {
+            if bounds::after_end(&self.cmp, range, unsafe { cur.as_ref().key() }.borrow()) {
+                cur = unsafe { cur.as_ref().left };
+            } else {
+                back = cur;
+                cur = unsafe { cur.as_ref().right };
+            }
+        }
+
+        // the ends cross when no key is in range
+        if self.is_nil(front)
+            || self.is_nil(back)
+            || unsafe {
+                self.cmp
+                    .compare(front.as_ref().key(), back.as_ref().key())
+                    .is_gt()
+            }
+        {
+            front = self.nil;
+            back = self.nil;
+        }
+
+        (front, back)
     }
+}
 
-    pub fn iter_mut(&mut self) -> RBTreeIterMut<'_, K, V> {
+// The iterators below hand out many values at once, whose `Augment` summaries could not be
+// refreshed while the references are alive, so they only exist without an `Augment`.
+// `map_values_in_place` edits every value of an augmented tree.
+impl<K: Key, V: Value, C: Comparator<K>> RBTree<K, V, C, NoAugment> {
+    pub fn iter_mut(&mut self) -> RBTreeIterMut<'_, K, V, C> {
         let first = self.inorder_successor(self.header);
 
         RBTreeIterMut {
@@ -164,6 +596,149 @@ impl<K: Key, V: Value> RBTree<K, V> {
             rb_tree_mut: self,
         }
     }
+
+    /// Gives mutable iterators over two key ranges at once, or `None` if the ranges share an
+    /// entry. Each node is reachable from at most one of the iterators, so the values they
+    /// hand out never alias. Ranges whose bounds overlap but hold no common key are accepted.
+    #[allow(clippy::type_complexity)]
+    pub fn range_mut_pair<Q, R1, R2>(
+        &mut self,
+        r1: R1,
+        r2: R2,
+    ) -> Option<(RBTreeRangeMut<'_, K, V, C>, RBTreeRangeMut<'_, K, V, C>)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        R1: RangeBounds<Q>,
+        R2: RangeBounds<Q>,
+    {
+        let (front1, back1) = self.range_ends(&r1);
+        let (front2, back2) = self.range_ends(&r2);
+
+        // both ends are nil for an empty range
+        if !self.is_nil(front1) && !self.is_nil(front2) {
+            let overlap = unsafe {
+                let cmp = &self.cmp;
+                cmp.compare(front1.as_ref().key(), back2.as_ref().key())
+                    .is_le()
+                    && cmp
+                        .compare(front2.as_ref().key(), back1.as_ref().key())
+                        .is_le()
+            };
+            if overlap {
+                return None;
+            }
+        }
+
+        let rb_tree_ref = &*self;
+        Some((
+            RBTreeRangeMut {
+                front: front1,
+                back: back1,
+                rb_tree_ref,
+                marker: PhantomData,
+            },
+            RBTreeRangeMut {
+                front: front2,
+                back: back2,
+                rb_tree_ref,
+                marker: PhantomData,
+            },
+        ))
+    }
+}
+
+/// An entry of [`RBTree::merge_join`]: a key found only in the left tree, only in the right
+/// tree, or in both with the value from each.
+#[derive(Debug, PartialEq)]
+pub enum MergeItem<'a, K, V> {
+    Left(&'a K, &'a V),
+    Right(&'a K, &'a V),
+    Both(&'a K, &'a V, &'a V),
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Walks `self` and `other` together in key order, pairing up equal keys, in O(n + m).
+    pub fn merge_join<'a>(
+        &'a self,
+        other: &'a RBTree<K, V, C, A>,
+    ) -> impl Iterator<Item = MergeItem<'a, K, V>> {
+        let mut left = self.iter().peekable();
+        let mut right = other.iter().peekable();
+
+        core::iter::from_fn(move || match (left.peek(), right.peek()) {
+            (Some(&(left_key, _)), Some(&(right_key, _))) => {
+                match self.cmp.compare(left_key, right_key) {
+                    Ordering::Less => {
+                        let (key, value) = left.next()?;
+                        Some(MergeItem::Left(key, value))
+                    }
+                    Ordering::Greater => {
+                        let (key, value) = right.next()?;
+                        Some(MergeItem::Right(key, value))
+                    }
+                    Ordering::Equal => {
+                        let (key, left_value) = left.next()?;
+                        let (_, right_value) = right.next()?;
+                        Some(MergeItem::Both(key, left_value, right_value))
+                    }
+                }
+            }
+            (Some(_), None) => left.next().map(|(key, value)| MergeItem::Left(key, value)),
+            (None, Some(_)) => right
+                .next()
+                .map(|(key, value)| MergeItem::Right(key, value)),
+            (None, None) => None,
+        })
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K> + Default, A: Augment<K, V>> FromIterator<(K, V)>
+    for RBTree<K, V, C, A>
+{
+    /// Later values overwrite earlier ones for repeated keys.
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut tree = RBTree::with_comparator(C::default());
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<K: Key, V: Value> From<BTreeMap<K, V>> for RBTree<K, V> {
+    /// The map's entries are already sorted, so this builds a balanced tree in O(n).
+    fn from(map: BTreeMap<K, V>) -> Self {
+        RBTree::from_sorted_iter(map)
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> Extend<(K, V)> for RBTree<K, V, C, A> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Key, V: Value> RBTree<K, V> {
+    /// Builds a tree from `iter`, merging the value of a repeated key into the stored one with
+    /// `combine(&mut stored, new)`.
+    pub fn from_iter_with<I, F>(iter: I, mut combine: F) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        F: FnMut(&mut V, V),
+    {
+        let mut tree = RBTree::new();
+        for (key, value) in iter {
+            match tree.entry(key) {
+                Entry::Occupied(mut entry) => combine(&mut entry.get_mut(), value),
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        }
+        tree
+    }
 }
 
 #[cfg(test)]
@@ -272,4 +847,130 @@ mod tests {
 
         // If we get here without segfault/panic, the test passes
     }
+
+    #[test]
+    fn test_iter_from_paginates_without_gaps() {
+        let mut tree = RBTree::new();
+        for i in 0..20 {
+            tree.insert(i * 3, i);
+        }
+
+        let mut pages = vec![];
+        let mut page: Vec<_> = tree.iter().take(3).map(|(k, _)| *k).collect();
+        while !page.is_empty() {
+            // resume at the key right after the last one seen
+            let next = page.last().unwrap() + 1;
+            pages.push(page);
+            page = tree.iter_from(&next).take(3).map(|(k, _)| *k).collect();
+        }
+
+        assert_eq!(pages.len(), 7);
+        assert_eq!(pages[1], vec![9, 12, 15]);
+        let keys: Vec<_> = pages.concat();
+        assert_eq!(keys, (0..20).map(|i| i * 3).collect::<Vec<_>>());
+
+        // a key that is present starts the iteration itself
+        assert_eq!(tree.iter_from(&30).next(), Some((&30, &10)));
+        assert_eq!(tree.iter_from(&58).next(), None);
+    }
+
+    #[test]
+    fn test_stack_iter_matches_successor_walk() {
+        let mut tree = RBTree::new();
+        for i in 0..1000 {
+            tree.insert((i * 7919) % 1000, i);
+        }
+        for i in (0..1000).step_by(7) {
+            tree.remove(&i);
+        }
+
+        // `range` still steps with `inorder_successor`
+        assert!(tree.iter().eq(tree.range::<i32, _>(..)));
+        for start in [-1, 0, 7, 8, 500, 998, 999, 1000] {
+            assert!(tree.iter_from(&start).eq(tree.range(start..)));
+        }
+        assert!(tree.keys().eq(tree.keys_range::<i32, _>(..)));
+
+        let empty = RBTree::<i32, i32>::new();
+        assert_eq!(empty.iter().next(), None);
+        assert_eq!(empty.iter_from(&0).next(), None);
+    }
+
+    #[test]
+    fn test_range() {
+        let tree = setup_tree();
+        let keys: Vec<_> = tree.range(5..15).map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![5, 7, 10, 12]);
+
+        let keys: Vec<_> = tree.range(4..=15).rev().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![15, 12, 10, 7, 5]);
+
+        assert_eq!(tree.range(..).count(), 7);
+        assert_eq!(tree.range(13..15).next(), None);
+        assert_eq!(tree.range(20..).next(), None);
+
+        // both ends meet in the middle
+        let mut range = tree.range(7..=12);
+        assert_eq!(range.next(), Some((&7, &"seven")));
+        assert_eq!(range.next_back(), Some((&12, &"twelve")));
+        assert_eq!(range.next(), Some((&10, &"ten")));
+        assert_eq!(range.next_back(), None);
+        assert_eq!(range.next(), None);
+    }
+
+    #[test]
+    fn test_keys_range() {
+        let mut tree = RBTree::new();
+        for i in 0..10 {
+            tree.insert(i, i * i);
+        }
+        let keys: Vec<_> = tree.keys_range(3..=7).copied().collect();
+        assert_eq!(keys, vec![3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_from_iter_overwrites_duplicates() {
+        let tree: RBTree<_, _> = [(2, "b"), (1, "a"), (2, "B")].into_iter().collect();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.get(&2), Some(&"B"));
+    }
+
+    #[test]
+    fn test_from_iter_with_sums_repeated_keys() {
+        let words = "a b a c b a".split(' ').map(|word| (word, 1));
+        let counts = RBTree::from_iter_with(words, |count, one| *count += one);
+
+        let items: Vec<_> = counts.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(items, vec![("a", 3), ("b", 2), ("c", 1)]);
+
+        // keep-first is a no-op merge
+        let first = RBTree::from_iter_with([(1, "x"), (1, "y")], |_, _| {});
+        assert_eq!(first.get(&1), Some(&"x"));
+    }
+
+    #[test]
+    fn test_iter_order_check_passes() {
+        let mut tree = RBTree::new();
+        for i in 0..500 {
+            tree.insert((i * 7919) % 500, i);
+        }
+        for i in (0..500).step_by(3) {
+            tree.remove(&i);
+        }
+
+        // would trip the debug assertion in `next` if the order were wrong
+        assert_eq!(tree.iter().count(), tree.len());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "out of order")]
+    fn test_iter_order_check_catches_swapped_keys() {
+        let tree = setup_tree();
+        let mut root = unsafe { tree.header.as_ref().right };
+        let mut left = unsafe { root.as_ref().left };
+        unsafe { core::mem::swap(root.as_mut().key_mut(), left.as_mut().key_mut()) };
+
+        for _ in tree.iter() {}
+    }
 }
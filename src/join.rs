@@ -0,0 +1,331 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{borrow::Borrow, mem::ManuallyDrop, ops::RangeBounds};
+
+use crate::{
+    Augment, Comparator, RBTree,
+    binary_tree::BinaryTree,
+    bounds,
+    node::{Color, Key, NodePtr, Value},
+};
+
+// Split and join work on detached subtrees: a subtree is a root pointer whose nodes satisfy the
+// red-black rules on their own (the root may be red). While rebalancing, the subtree being
+// modified is hung under `header` so the usual fixup code can see where the root is.
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Removes every entry whose key lies in `range` by splitting the range out as a subtree
+    /// and joining what is left, instead of removing entries one by one. Returns the number of
+    /// entries removed.
+    ///
+    /// Costs O(log² n) plus the time to free the removed nodes.
+    pub fn remove_range_fast<Q, R>(&mut self, range: R) -> usize
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        R: RangeBounds<Q>,
+    {
+        let root = unsafe { self.header.as_ref().right };
+        let (below, rest) = self.split(root, &|cmp: &C, key: &K| {
+            bounds::before_start(cmp, &range, key.borrow())
+        });
+        let (inside, above) = self.split(rest, &|cmp: &C, key: &K| {
+            !bounds::after_end(cmp, &range, key.borrow())
+        });
+
+        self.join2(below, above);
+        let removed = self.free_subtree(inside);
+        self.len -= removed;
+        self.debug_check();
+        removed
+    }
+
+    /// Appends entries whose keys are in strictly ascending order. When every key is greater
+    /// than the current largest key, the entries are built into a balanced subtree in O(m) and
+    /// joined onto the tree in O(log n); otherwise each entry is inserted one at a time.
+    pub fn extend_sorted<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let pairs: Vec<(K, V)> = iter.into_iter().collect();
+        let ascending = pairs
+            .windows(2)
+            .all(|w| self.cmp.compare(&w[0].0, &w[1].0).is_lt());
+        let after_max = match (self.max_key(), pairs.first()) {
+            (Some(max), Some((first, _))) => self.cmp.compare(max, first).is_lt(),
+            _ => true,
+        };
+        if !ascending || !after_max {
+            // `insert` runs the check itself
+            for (key, value) in pairs {
+                self.insert(key, value);
+            }
+            return;
+        }
+
+        let nodes: Vec<_> = pairs
+            .into_iter()
+            .map(|(key, value)| self.new_node(key, value))
+            .collect();
+        let left = unsafe { self.header.as_ref().right };
+        let right = self.link_balanced(&nodes);
+        self.join2(left, right);
+        self.len += nodes.len();
+        self.debug_check();
+    }
+
+    /// Moves every entry with a key greater than `key` into a new tree, leaving the entries up
+    /// to and including `key` in `self`.
+    ///
+    /// Splitting takes O(log² n); the moved nodes are then relinked to the new tree's
+    /// sentinel, which costs O(m) for the m entries moved.
+    pub fn split_off_after<Q>(&mut self, key: &Q) -> RBTree<K, V, C, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q> + Clone,
+    {
+        let root = unsafe { self.header.as_ref().right };
+        let (left, right) =
+            self.split(root, &|cmp: &C, k: &K| cmp.compare(k.borrow(), key).is_le());
+        self.color_black(left);
+        self.set_root(left);
+        self.last_inserted = self.nil;
+
+        let mut other = self.empty_like();
+        if self.is_nil(right) {
+            self.debug_check();
+            return other;
+        }
+
+        let moved = unsafe { right.as_ref().size };
+        self.len -= moved;
+        self.debug_check();
+        let mut nodes = vec![];
+        self._traverse(right, &mut |node| nodes.push(node));
+        for mut node in nodes {
+            let node = unsafe { node.as_mut() };
+            if self.is_nil(node.left) {
+                node.left = other.nil;
+            }
+            if self.is_nil(node.right) {
+                node.right = other.nil;
+            }
+        }
+        other.color_black(right);
+        other.set_root(right);
+        other.len = moved;
+        other.debug_check();
+        other
+    }
+
+    /// Removes every entry whose key lies in `range`, one at a time. Returns the number of
+    /// entries removed.
+    pub fn remove_range<Q, R>(&mut self, range: R) -> usize
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        R: RangeBounds<Q>,
+    {
+        let mut to_remove = vec![];
+        let mut cur = self.first_node();
+        while !self.is_nil(cur) {
+            let key = unsafe { cur.as_ref().key() }.borrow();
+            if bounds::after_end(&self.cmp, &range, key) {
+                break;
+            }
+            if !bounds::before_start(&self.cmp, &range, key) {
+                to_remove.push(cur);
+            }
+            cur = self.inorder_successor(cur);
+        }
+
+        // ascending order, see `try_retain`
+        let removed = to_remove.len();
+        for node in to_remove {
+            self.remove_node(node);
+        }
+        removed
+    }
+
+    /// Splits the subtree at `root` into the nodes whose keys satisfy `goes_left` and the rest.
+    /// `goes_left` is passed the tree's comparator and must hold for a prefix of the keys in
+    /// order.
+    pub(crate) fn split<F>(
+        &mut self,
+        root: NodePtr<K, V, A>,
+        goes_left: &F,
+    ) -> (NodePtr<K, V, A>, NodePtr<K, V, A>)
+    where
+        F: Fn(&C, &K) -> bool,
+    {
+        if self.is_nil(root) {
+            return (self.nil, self.nil);
+        }
+
+        let (left, right) = unsafe { (root.as_ref().left, root.as_ref().right) };
+        if goes_left(&self.cmp, unsafe { root.as_ref().key() }) {
+            let (middle, right) = self.split(right, goes_left);
+            (self.join(left, root, middle), right)
+        } else {
+            let (left, middle) = self.split(left, goes_left);
+            (left, self.join(middle, root, right))
+        }
+    }
+
+    /// Joins the subtrees `left` and `right` with `mid` between them, where every key in `left`
+    /// is less than `mid`'s key and every key in `right` is greater. Returns the new root,
+    /// which is also left installed under `header`.
+    pub(crate) fn join(
+        &mut self,
+        left: NodePtr<K, V, A>,
+        mut mid: NodePtr<K, V, A>,
+        right: NodePtr<K, V, A>,
+    ) -> NodePtr<K, V, A> {
+        // A red root can always be turned black, which makes the black heights easy to compare.
+        self.color_black(left);
+        self.color_black(right);
+        let left_height = self.black_height(left);
+        let right_height = self.black_height(right);
+
+        if left_height == right_height {
+            self.link_children(mid, left, right);
+            self.color_black(mid);
+            self.set_root(mid);
+            return mid;
+        }
+
+        // Hang `mid` in place of the black node on the taller tree's inner spine whose black
+        // height equals the shorter tree's. Colored red, `mid` keeps all black heights equal
+        // and can only break the red-red rule with its parent, which `insert_fixup` repairs.
+        unsafe { mid.as_mut().color = Color::Red };
+        if left_height > right_height {
+            self.set_root(left);
+            let (mut parent, cur) =
+                self.spine_node(left, left_height, right_height, |node| unsafe {
+                    node.as_ref().right
+                });
+            self.link_children(mid, cur, right);
+            unsafe {
+                mid.as_mut().parent = parent;
+                parent.as_mut().right = mid;
+            }
+        } else {
+            self.set_root(right);
+            let (mut parent, cur) =
+                self.spine_node(right, right_height, left_height, |node| unsafe {
+                    node.as_ref().left
+                });
+            self.link_children(mid, left, cur);
+            unsafe {
+                mid.as_mut().parent = parent;
+                parent.as_mut().left = mid;
+            }
+        }
+
+        self.update_upward(mid);
+        self.insert_fixup(mid);
+        unsafe { self.header.as_ref().right }
+    }
+
+    /// Joins `left` and `right` where every key in `left` is less than every key in `right`,
+    /// and installs the result as the whole tree.
+    pub(crate) fn join2(
+        &mut self,
+        left: NodePtr<K, V, A>,
+        right: NodePtr<K, V, A>,
+    ) -> NodePtr<K, V, A> {
+        if self.is_nil(right) {
+            self.set_root(left);
+            return left;
+        }
+
+        // borrow the smallest node of `right` as the middle node
+        self.set_root(right);
+        let min = self.first_node();
+        self.unlink_node(min);
+        let right = unsafe { self.header.as_ref().right };
+        self.join(left, min, right)
+    }
+
+    /// Walks from `root` (black height `height`) along `next` to the first black node of black
+    /// height `target`, which may be nil. Returns that node's parent and the node.
+    fn spine_node<F>(
+        &self,
+        root: NodePtr<K, V, A>,
+        mut height: usize,
+        target: usize,
+        next: F,
+    ) -> (NodePtr<K, V, A>, NodePtr<K, V, A>)
+    where
+        F: Fn(NodePtr<K, V, A>) -> NodePtr<K, V, A>,
+    {
+        let mut parent = self.header;
+        let mut cur = root;
+        loop {
+            if unsafe { cur.as_ref().color } == Color::Black {
+                if height == target {
+                    return (parent, cur);
+                }
+                height -= 1;
+            }
+            parent = cur;
+            cur = next(cur);
+        }
+    }
+
+    /// Number of black nodes on any path from `root` down to a leaf, not counting nil.
+    fn black_height(&self, root: NodePtr<K, V, A>) -> usize {
+        let mut height = 0;
+        let mut cur = root;
+        while !self.is_nil(cur) {
+            if unsafe { cur.as_ref().color } == Color::Black {
+                height += 1;
+            }
+            cur = unsafe { cur.as_ref().left };
+        }
+        height
+    }
+
+    fn link_children(
+        &mut self,
+        mut node: NodePtr<K, V, A>,
+        mut left: NodePtr<K, V, A>,
+        mut right: NodePtr<K, V, A>,
+    ) {
+        unsafe {
+            node.as_mut().left = left;
+            node.as_mut().right = right;
+            if !self.is_nil(left) {
+                left.as_mut().parent = node;
+            }
+            if !self.is_nil(right) {
+                right.as_mut().parent = node;
+            }
+        }
+        self.update_node(node);
+    }
+
+    fn set_root(&mut self, mut root: NodePtr<K, V, A>) {
+        unsafe {
+            self.header.as_mut().right = root;
+            if !self.is_nil(root) {
+                root.as_mut().parent = self.header;
+            }
+        }
+    }
+
+    /// Drops the entries of a detached subtree and frees its nodes. Returns how many there were.
+    fn free_subtree(&mut self, root: NodePtr<K, V, A>) -> usize {
+        self.last_inserted = self.nil;
+        let mut nodes = vec![];
+        self._traverse(root, &mut |node| nodes.push(node));
+        for &node in &nodes {
+            unsafe {
+                let mut b = Box::from_raw(node.as_ptr());
+                ManuallyDrop::drop(b.key.assume_init_mut());
+                ManuallyDrop::drop(b.value.assume_init_mut());
+                b.summary.assume_init_drop();
+            }
+        }
+        nodes.len()
+    }
+}
@@ -1,40 +1,138 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
 use crate::{
     binary_search_tree::{BinarySearchTree, InsertResult},
     binary_tree::{BinaryTree, NodePosition},
-    node::{Color, Key, NodePtr, RBNode, Value},
+    builder::ValidateHook,
+    node::{Key, NodePtr, RBNode, Value},
+};
+use alloc::{
+    alloc::Layout,
+    boxed::Box,
+    collections::{BinaryHeap, TryReserveError},
+    string::String,
+    vec,
+    vec::Vec,
 };
-use std::{
+use core::{
     borrow::Borrow,
-    fmt::{Debug, Display},
+    cmp::{Ordering, Reverse},
+    fmt::{Debug, Display, Write},
+    iter::Sum,
     mem::{ManuallyDrop, MaybeUninit},
+    ops::{ControlFlow, RangeBounds},
     ptr::NonNull,
 };
 
+mod augment;
 mod binary_search_tree;
 mod binary_tree;
+mod bounds;
+mod builder;
+mod compare;
+mod cursor;
+mod entry;
+mod fingerprint;
+mod guard;
 mod iter;
+mod join;
 mod node;
+mod node_ref;
+mod order_statistics;
+mod raw;
+mod set;
+mod sum;
+#[cfg(feature = "std")]
+mod sync;
+#[cfg(any(test, feature = "testing"))]
+mod testing;
 mod validate;
 
 // Re-export the validation trait for external use
 use binary_search_tree::validate::BSTValidator;
 
 // Re-export our simple BinarySearchTree implementation
-pub use binary_search_tree::binary_search_tree_impl::BinarySearchTree as SimpleBST;
+pub use augment::{Augment, NoAugment};
+pub use binary_search_tree::binary_search_tree_impl::{
+    BinarySearchTree as SimpleBST, Entry as SimpleBSTEntry,
+    OccupiedEntry as SimpleBSTOccupiedEntry, VacantEntry as SimpleBSTVacantEntry,
+};
+pub use builder::RBTreeBuilder;
+pub use compare::{Comparator, OrdComparator};
+pub use cursor::CursorMut;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use guard::{ValueGuard, ValueMut};
+pub use iter::MergeItem;
+pub use node::Color;
+pub use node_ref::NodeRef;
+pub use raw::RawRBTree;
+pub use set::RBSet;
+pub use sum::SumAugment;
+#[cfg(feature = "std")]
+pub use sync::RwRBTree;
 
 #[derive(Debug)]
-pub struct RBTree<K: Key, V: Value> {
-    header: NodePtr<K, V>,
-    nil: NodePtr<K, V>,
+pub struct RBTree<K: Key, V: Value, C: Comparator<K> = OrdComparator, A: Augment<K, V> = NoAugment>
+{
+    header: NodePtr<K, V, A>,
+    nil: NodePtr<K, V, A>,
     len: usize,
+    /// Preallocated nodes (with uninitialized key and value) waiting to be reused by `new_node`
+    spare: Vec<NodePtr<K, V, A>>,
+    /// Node written by the last `insert_with_hint`, or nil. Reset whenever a node is unlinked,
+    /// since removal can free it or move another entry into it.
+    last_inserted: NodePtr<K, V, A>,
+    /// Rotations performed since the tree was created or `reset_rotation_count` was called
+    rotations: u64,
+    /// Orders the keys, see `RBTreeBuilder::comparator`
+    cmp: C,
+    /// Checks the tree after every change to its links, see `RBTreeBuilder::debug_validate`
+    validate_hook: Option<ValidateHook<K, V, C, A>>,
+}
+
+/// The error returned by [`RBTree::try_get`] when the key is absent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyNotFound;
+
+impl Display for KeyNotFound {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "key not found")
+    }
 }
 
+impl core::error::Error for KeyNotFound {}
+
 impl<K: Key, V: Value> RBTree<K, V> {
     pub fn new() -> Self {
+        Self::with_comparator(OrdComparator)
+    }
+
+    /// Creates a tree holding just `key` and `value`. The single node is linked in as a black
+    /// root directly, without going through insertion.
+    pub fn singleton(key: K, value: V) -> Self {
+        let mut tree = Self::new();
+        let mut node = tree.new_node(key, value);
+        unsafe {
+            node.as_mut().color = Color::Black;
+            node.as_mut().parent = tree.header;
+            tree.header.as_mut().right = node;
+        }
+        tree.len = 1;
+        tree
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Creates an empty tree ordered by `cmp`, see `RBTreeBuilder::comparator`.
+    pub(crate) fn with_comparator(cmp: C) -> Self {
         let mut nil_node = Box::new(RBNode {
             key: MaybeUninit::uninit(),
             value: MaybeUninit::uninit(),
+            summary: MaybeUninit::uninit(),
             color: Color::Black,
+            size: 0,
             left: NonNull::dangling(),
             right: NonNull::dangling(),
             parent: NonNull::dangling(),
@@ -50,7 +148,9 @@ impl<K: Key, V: Value> RBTree<K, V> {
         let header_node = Box::new(RBNode {
             key: MaybeUninit::uninit(),
             value: MaybeUninit::uninit(),
+            summary: MaybeUninit::uninit(),
             color: Color::Black,
+            size: 0,
             left: leaked_nil_ptr,
             right: leaked_nil_ptr,
             parent: leaked_nil_ptr,
@@ -61,35 +161,137 @@ impl<K: Key, V: Value> RBTree<K, V> {
             header: leaked_header_ptr,
             nil: leaked_nil_ptr,
             len: 0,
+            spare: Vec::new(),
+            last_inserted: leaked_nil_ptr,
+            rotations: 0,
+            cmp,
+            validate_hook: None,
         }
     }
 
-    fn is_nil(&self, node: NodePtr<K, V>) -> bool {
+    /// Creates an empty tree configured like `self`: same comparator, `Augment` and
+    /// `debug_validate` check.
+    fn empty_like(&self) -> Self
+    where
+        C: Clone,
+    {
+        let mut tree = Self::with_comparator(self.cmp.clone());
+        tree.validate_hook = self.validate_hook;
+        tree
+    }
+
+    fn is_nil(&self, node: NodePtr<K, V, A>) -> bool {
         self.nil == node
     }
 
-    fn is_header(&self, node: NodePtr<K, V>) -> bool {
+    fn is_header(&self, node: NodePtr<K, V, A>) -> bool {
         self.header == node
     }
 
-    fn new_node(&self, key: K, value: V) -> NodePtr<K, V> {
-        let node = Box::new(RBNode {
+    fn new_node(&mut self, key: K, value: V) -> NodePtr<K, V, A> {
+        // a lone node's summary; linking it in recomputes its ancestors
+        let summary = A::summarize(&key, &value, None, None);
+        let node = RBNode {
+            summary: MaybeUninit::new(summary),
             key: MaybeUninit::new(ManuallyDrop::new(key)),
             value: MaybeUninit::new(ManuallyDrop::new(value)),
             color: Color::Red,
+            size: 1,
             left: self.nil,
             right: self.nil,
             parent: self.nil,
-        });
+        };
 
-        NonNull::from(Box::leak(node))
+        match self.spare.pop() {
+            Some(spare) => {
+                unsafe { spare.as_ptr().write(node) };
+                spare
+            }
+            None => NonNull::from(Box::leak(Box::new(node))),
+        }
+    }
+
+    /// Allocates `additional` empty nodes into the spare pool.
+    fn allocate_spare(&mut self, additional: usize) {
+        self.spare.reserve_exact(additional);
+        for _ in 0..additional {
+            let node = Box::new(RBNode {
+                key: MaybeUninit::uninit(),
+                value: MaybeUninit::uninit(),
+                summary: MaybeUninit::uninit(),
+                color: Color::Red,
+                size: 0,
+                left: self.nil,
+                right: self.nil,
+                parent: self.nil,
+            });
+            self.spare.push(NonNull::from(Box::leak(node)));
+        }
+    }
+
+    /// Makes sure the spare pool holds at least one node, reporting allocation failure instead
+    /// of aborting.
+    fn try_reserve_node(&mut self) -> Result<(), TryReserveError> {
+        if !self.spare.is_empty() {
+            return Ok(());
+        }
+        self.spare.try_reserve(1)?;
+
+        // `Box::try_new` is unstable, so allocate the node's layout directly; nodes are freed
+        // through `Box`, which uses the same layout.
+        let layout = Layout::new::<RBNode<K, V, A>>();
+        let node = unsafe { alloc::alloc::alloc(layout) } as *mut RBNode<K, V, A>;
+        let Some(node) = NonNull::new(node) else {
+            // a `TryReserveError` can't be built directly, so take one from a reservation
+            // that can never succeed
+            return Err(Vec::<RBNode<K, V, A>>::new()
+                .try_reserve_exact(usize::MAX)
+                .unwrap_err());
+        };
+        unsafe {
+            node.as_ptr().write(RBNode {
+                key: MaybeUninit::uninit(),
+                value: MaybeUninit::uninit(),
+                summary: MaybeUninit::uninit(),
+                color: Color::Red,
+                size: 0,
+                left: self.nil,
+                right: self.nil,
+                parent: self.nil,
+            });
+        }
+        self.spare.push(node);
+        Ok(())
+    }
+
+    /// Allocates exactly enough spare nodes that `additional` more entries can be inserted
+    /// without allocating. Nodes already in the spare pool count toward `additional`, so nothing
+    /// beyond `len() + additional` is ever allocated.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.allocate_spare(additional.saturating_sub(self.spare.len()));
     }
 
-    pub fn traverse<F: FnMut(NodePtr<K, V>)>(&self, mut f: F) {
+    /// Number of rotations the tree has performed while rebalancing, for measuring how much
+    /// restructuring a workload causes.
+    pub fn rotation_count(&self) -> u64 {
+        self.rotations
+    }
+
+    pub fn reset_rotation_count(&mut self) {
+        self.rotations = 0;
+    }
+
+    /// Number of entries the tree can hold without allocating new nodes.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.len + self.spare.len()
+    }
+
+    pub fn traverse<F: FnMut(NodePtr<K, V, A>)>(&self, mut f: F) {
         self._traverse(unsafe { self.header.as_ref().right }, &mut f);
     }
 
-    fn _traverse<F: FnMut(NodePtr<K, V>)>(&self, node: NodePtr<K, V>, f: &mut F) {
+    fn _traverse<F: FnMut(NodePtr<K, V, A>)>(&self, node: NodePtr<K, V, A>, f: &mut F) {
         if self.is_nil(node) {
             return;
         }
@@ -99,42 +301,919 @@ impl<K: Key, V: Value> RBTree<K, V> {
         self._traverse(unsafe { node.as_ref().right }, f);
     }
 
-    pub(crate) fn search<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+    /// Calls `f` with each entry in key order, along with the node's depth (0 for the root) and
+    /// color, for rendering or analyzing the tree's shape.
+    pub fn traverse_detailed<F: FnMut(&K, &V, usize, Color)>(&self, mut f: F) {
+        self._traverse_detailed(unsafe { self.header.as_ref().right }, 0, &mut f);
+    }
+
+    fn _traverse_detailed<F: FnMut(&K, &V, usize, Color)>(
+        &self,
+        node: NodePtr<K, V, A>,
+        depth: usize,
+        f: &mut F,
+    ) {
+        if self.is_nil(node) {
+            return;
+        }
+
+        let node_ref = unsafe { node.as_ref() };
+        self._traverse_detailed(node_ref.left, depth + 1, f);
+        let (key, value) = unsafe { (node_ref.key(), node_ref.value()) };
+        f(key, value, depth, node_ref.color);
+        self._traverse_detailed(node_ref.right, depth + 1, f);
+    }
+
+    /// Calls `f` with each entry in key order and the node's depth (0 for the root), with the
+    /// value mutable, e.g. to store depth-dependent data in the values. Like
+    /// `map_values_in_place`, an `Augment` summary is recomputed bottom-up along the way.
+    pub fn for_each_mut_with_depth<F: FnMut(&K, &mut V, usize)>(&mut self, mut f: F) {
+        let root = unsafe { self.header.as_ref().right };
+        self._for_each_mut_with_depth(root, 0, &mut f);
+    }
+
+    fn _for_each_mut_with_depth<F: FnMut(&K, &mut V, usize)>(
+        &mut self,
+        mut node: NodePtr<K, V, A>,
+        depth: usize,
+        f: &mut F,
+    ) {
+        if self.is_nil(node) {
+            return;
+        }
+
+        let (left, right) = unsafe { (node.as_ref().left, node.as_ref().right) };
+        self._for_each_mut_with_depth(left, depth + 1, f);
+        unsafe {
+            let node = node.as_mut();
+            f(
+                node.key.assume_init_ref(),
+                node.value.assume_init_mut(),
+                depth,
+            );
+        }
+        self._for_each_mut_with_depth(right, depth + 1, f);
+
+        // both children are final by now
+        if Self::has_summary() {
+            self.update_node(node);
+        }
+    }
+
+    /// Applies `f` to every value in key order, keeping the keys and the tree's shape. Unlike
+    /// `iter_mut`, an `Augment` summary is recomputed bottom-up along the way, so `f` can
+    /// change the fields a summary is computed from.
+    pub fn map_values_in_place<F: FnMut(&K, &mut V)>(&mut self, mut f: F) {
+        let root = unsafe { self.header.as_ref().right };
+        self._map_values_in_place(root, &mut f);
+    }
+
+    fn _map_values_in_place<F: FnMut(&K, &mut V)>(
+        &mut self,
+        mut node: NodePtr<K, V, A>,
+        f: &mut F,
+    ) {
+        if self.is_nil(node) {
+            return;
+        }
+
+        let (left, right) = unsafe { (node.as_ref().left, node.as_ref().right) };
+        self._map_values_in_place(left, f);
+        unsafe {
+            let node = node.as_mut();
+            f(node.key.assume_init_ref(), node.value.assume_init_mut());
+        }
+        self._map_values_in_place(right, f);
+
+        // both children are final by now
+        if Self::has_summary() {
+            self.update_node(node);
+        }
+    }
+
+    pub(crate) fn search<Q>(&self, key: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
-        Q: Ord,
+        Q: ?Sized,
+        C: Comparator<Q>,
     {
         BinarySearchTree::search(self, key)
     }
 
-    pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
-        Q: Ord,
+        Q: ?Sized,
+        C: Comparator<Q>,
     {
         self.search(key)
     }
 
-    pub fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    /// Returns the value for `key`, mutable through the returned [`ValueMut`].
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<ValueMut<'_, K, V, C, A>>
     where
         K: Borrow<Q>,
-        Q: Ord,
+        Q: ?Sized,
+        C: Comparator<Q>,
     {
-        self.search_mut(key)
+        let node = self.search_node(key);
+        if self.is_nil(node) {
+            return None;
+        }
+
+        Some(ValueMut::new(self, node))
+    }
+
+    /// Looks up `key` like `get`, and moves the node one level closer to the root when that
+    /// can be done without breaking the red-black rules, so repeatedly accessed keys drift
+    /// shallower.
+    ///
+    /// The only move used is rotating a red node above its black parent when its sibling is
+    /// black, then swapping the two colors: black heights and the red rule are unchanged.
+    /// Any other access leaves the tree as it is.
+    pub fn get_promote<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let mut node = self.search_node(key);
+        if self.is_nil(node) {
+            return None;
+        }
+
+        let mut parent = unsafe { node.as_ref().parent };
+        if !self.is_header(parent)
+            && unsafe { node.as_ref().color } == Color::Red
+            && unsafe { parent.as_ref().color } == Color::Black
+            && unsafe { self.sibling(node).as_ref().color } == Color::Black
+        {
+            match self.get_node_position(node) {
+                NodePosition::Left => self.rotate_right(parent),
+                NodePosition::Right => self.rotate_left(parent),
+            }
+            unsafe {
+                node.as_mut().color = Color::Black;
+                parent.as_mut().color = Color::Red;
+            }
+            self.debug_check();
+        }
+
+        Some(unsafe { node.as_ref().value() })
+    }
+
+    /// Returns an owned clone of the value for `key`. With `V = Arc<T>` or `Rc<T>` this is a cheap
+    /// reference-count bump, which suits many keys sharing large values.
+    pub fn get_cloned<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        V: Clone,
+    {
+        self.search(key).cloned()
+    }
+
+    /// Like `get`, but reports a missing key as a [`KeyNotFound`] error, so lookups can be
+    /// chained with `?` in functions returning `Result`.
+    pub fn try_get<Q>(&self, key: &Q) -> Result<&V, KeyNotFound>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.search(key).ok_or(KeyNotFound)
+    }
+
+    /// Swaps the values stored under keys `a` and `b`. Returns `false`, leaving the tree
+    /// unchanged, if either key is missing.
+    pub fn swap_values<Q>(&mut self, a: &Q, b: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let a = self.search_node(a);
+        let b = self.search_node(b);
+        if self.is_nil(a) || self.is_nil(b) {
+            return false;
+        }
+
+        // `a` and `b` may be the same node, which `ptr::swap` allows
+        unsafe {
+            core::ptr::swap(
+                (*a.as_ptr()).value.as_mut_ptr(),
+                (*b.as_ptr()).value.as_mut_ptr(),
+            );
+        }
+        self.value_replaced(a);
+        self.value_replaced(b);
+        true
+    }
+
+    /// Whether any key lies within `range`. Stops at the first matching node on the way down
+    /// instead of walking the range.
+    pub fn any_in_range<Q, R>(&self, range: R) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        R: RangeBounds<Q>,
+    {
+        let mut cur = unsafe { self.header.as_ref().right };
+
+        while !self.is_nil(cur) {
+            let node = unsafe { cur.as_ref() };
+            let k = unsafe { node.key().borrow() };
+
+            if bounds::before_start(&self.cmp, &range, k) {
+                cur = node.right;
+            } else if bounds::after_end(&self.cmp, &range, k) {
+                cur = node.left;
+            } else {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Returns the entry with the smallest key greater than `key`, with a mutable value.
+    #[allow(clippy::type_complexity)]
+    pub fn successor_mut<Q>(&mut self, key: &Q) -> Option<(&K, ValueMut<'_, K, V, C, A>)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let node = self.successor_node(key);
+        self.key_value_mut(node)
+    }
+
+    /// Returns the entry with the largest key less than `key`, with a mutable value.
+    #[allow(clippy::type_complexity)]
+    pub fn predecessor_mut<Q>(&mut self, key: &Q) -> Option<(&K, ValueMut<'_, K, V, C, A>)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let node = self.predecessor_node(key);
+        self.key_value_mut(node)
+    }
+
+    /// Returns the entries just below and just above `key`, whether or not `key` itself is
+    /// present, in a single descent.
+    #[allow(clippy::type_complexity)]
+    pub fn neighbors<Q>(&self, key: &Q) -> (Option<(&K, &V)>, Option<(&K, &V)>)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        // the last nodes the descent passed on its right / left
+        let mut below = self.nil;
+        let mut above = self.nil;
+        let mut cur = unsafe { self.header.as_ref().right };
+
+        while !self.is_nil(cur) {
+            let k = unsafe { cur.as_ref().key().borrow() };
+            let ord = self.cmp.compare(key, k);
+
+            if ord.is_eq() {
+                // the neighbors lie in the subtrees if those are non-empty
+                if !self.is_nil(unsafe { cur.as_ref().left }) {
+                    below = self.inorder_predecessor(cur);
+                }
+                if !self.is_nil(unsafe { cur.as_ref().right }) {
+                    above = self.inorder_successor(cur);
+                }
+                break;
+            }
+
+            if ord.is_lt() {
+                above = cur;
+                cur = unsafe { cur.as_ref().left };
+            } else {
+                below = cur;
+                cur = unsafe { cur.as_ref().right };
+            }
+        }
+
+        let entry = |node: NodePtr<K, V, A>| {
+            (!self.is_nil(node)).then(|| unsafe { (node.as_ref().key(), node.as_ref().value()) })
+        };
+        (entry(below), entry(above))
+    }
+
+    /// Returns the keys a lookup of `key` compares against, from the root down to `key` itself
+    /// or, if it is absent, to the last node before the search falls off the tree.
+    pub fn search_path<Q>(&self, key: &Q) -> Vec<K>
+    where
+        K: Borrow<Q> + Clone,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let mut path = Vec::new();
+        let mut cur = unsafe { self.header.as_ref().right };
+        while !self.is_nil(cur) {
+            let k = unsafe { cur.as_ref().key() };
+            path.push(k.clone());
+            let ord = self.cmp.compare(key, k.borrow());
+            if ord.is_eq() {
+                break;
+            }
+
+            cur = if ord.is_lt() {
+                unsafe { cur.as_ref().left }
+            } else {
+                unsafe { cur.as_ref().right }
+            };
+        }
+        path
+    }
+
+    /// Reduces the values in key order, like `Iterator::fold` but without the key.
+    pub fn fold_values<B, F: FnMut(B, &V) -> B>(&self, init: B, mut f: F) -> B {
+        self.iter().fold(init, |acc, (_, value)| f(acc, value))
     }
 
-    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
-        match self.bs_insert(key, value) {
-            InsertResult::Old(old_value) => Some(old_value),
-            InsertResult::New(red_node) => {
-                self.insert_fixup(red_node);
-                self.len += 1;
-                None
-            }
+    /// Calls `f` on each entry in key order, stopping at the first `Err` and returning it. The
+    /// walk follows parent links instead of recursing, so bailing out leaves nothing to unwind.
+    pub fn try_for_each<E, F: FnMut(&K, &V) -> Result<(), E>>(&self, mut f: F) -> Result<(), E> {
+        let mut node = self.first_node();
+        while !self.is_nil(node) {
+            unsafe { f(node.as_ref().key(), node.as_ref().value())? };
+            node = self.inorder_successor(node);
+        }
+
+        Ok(())
+    }
+
+    /// Sums all values. Returns `V`'s empty sum for an empty tree.
+    pub fn sum_values(&self) -> V
+    where
+        V: Sum + Clone,
+    {
+        self.iter().map(|(_, value)| value.clone()).sum()
+    }
+
+    /// Returns the largest key `<= start` that is not in the tree, stepping down from `start`
+    /// with `prev`. Useful for allocating ids downward from a maximum.
+    pub fn last_gap<F: Fn(&K) -> K>(&self, start: K, prev: F) -> K {
+        let mut candidate = start;
+        let mut node = self.floor_node(&candidate);
+
+        // walk down the run of consecutive keys ending at `start`
+        while !self.is_nil(node)
+            && self
+                .cmp
+                .compare(unsafe { node.as_ref().key() }, &candidate)
+                .is_eq()
+        {
+            candidate = prev(&candidate);
+            node = self.inorder_predecessor(node);
+        }
+
+        candidate
+    }
+
+    /// Number of nodes whose children are both nil.
+    pub fn count_leaves(&self) -> usize {
+        self.count_leaves_and_internal().0
+    }
+
+    /// Number of nodes with at least one non-nil child. Together with `count_leaves` this adds
+    /// up to `len()`.
+    pub fn count_internal(&self) -> usize {
+        self.count_leaves_and_internal().1
+    }
+
+    fn count_leaves_and_internal(&self) -> (usize, usize) {
+        let mut leaves = 0;
+        let mut internal = 0;
+        self.traverse(|node| unsafe {
+            if self.is_nil(node.as_ref().left) && self.is_nil(node.as_ref().right) {
+                leaves += 1;
+            } else {
+                internal += 1;
+            }
+        });
+        (leaves, internal)
+    }
+
+    /// Number of edges from the root to the node holding `key` (the root is at depth 0), or
+    /// `None` if `key` is absent. Useful to see how long a particular lookup walks.
+    pub fn depth_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let mut depth = 0;
+        let mut cur = unsafe { self.header.as_ref().right };
+
+        while !self.is_nil(cur) {
+            let k = unsafe { cur.as_ref().key().borrow() };
+            let ord = self.cmp.compare(key, k);
+
+            if ord.is_eq() {
+                return Some(depth);
+            }
+
+            cur = if ord.is_lt() {
+                unsafe { cur.as_ref().left }
+            } else {
+                unsafe { cur.as_ref().right }
+            };
+            depth += 1;
+        }
+
+        None
+    }
+
+    /// Returns the entry whose key is closest to `key`: the key itself if present, otherwise
+    /// the nearer of its neighbors on either side, found in one descent. `Ord` alone can't
+    /// measure distance, so `closer(a, b)` compares two candidate keys by their distance to
+    /// `key`, returning `Less` when `a` is closer. Ties go to the smaller key.
+    pub fn closest_by<Q, F>(&self, key: &Q, closer: F) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        F: Fn(&K, &K) -> Ordering,
+    {
+        let mut below = self.nil;
+        let mut above = self.nil;
+        let mut cur = unsafe { self.header.as_ref().right };
+
+        while !self.is_nil(cur) {
+            let k = unsafe { cur.as_ref().key().borrow() };
+            let ord = self.cmp.compare(key, k);
+
+            if ord.is_eq() {
+                return unsafe { Some((cur.as_ref().key(), cur.as_ref().value())) };
+            }
+
+            if ord.is_lt() {
+                above = cur;
+                cur = unsafe { cur.as_ref().left };
+            } else {
+                below = cur;
+                cur = unsafe { cur.as_ref().right };
+            }
+        }
+
+        let node = if self.is_nil(below) {
+            above
+        } else if self.is_nil(above) {
+            below
+        } else {
+            let (below_key, above_key) = unsafe { (below.as_ref().key(), above.as_ref().key()) };
+            if closer(above_key, below_key) == Ordering::Less {
+                above
+            } else {
+                below
+            }
+        };
+
+        if self.is_nil(node) {
+            return None;
+        }
+
+        unsafe { Some((node.as_ref().key(), node.as_ref().value())) }
+    }
+
+    /// Finds the node holding the largest key less than or equal to `key`.
+    fn floor_node<Q>(&self, key: &Q) -> NodePtr<K, V, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let mut floor = self.nil;
+        let mut cur = unsafe { self.header.as_ref().right };
+
+        while !self.is_nil(cur) {
+            let k = unsafe { cur.as_ref().key().borrow() };
+            let ord = self.cmp.compare(key, k);
+
+            if ord.is_eq() {
+                return cur;
+            }
+
+            if ord.is_lt() {
+                cur = unsafe { cur.as_ref().left };
+            } else {
+                floor = cur;
+                cur = unsafe { cur.as_ref().right };
+            }
+        }
+
+        floor
+    }
+
+    /// Finds the node holding the smallest key greater than `key`, whether or not `key` is present.
+    fn successor_node<Q>(&self, key: &Q) -> NodePtr<K, V, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let mut parent = self.header;
+        let mut cur = unsafe { self.header.as_ref().right };
+
+        while !self.is_nil(cur) {
+            let k = unsafe { cur.as_ref().key().borrow() };
+            let ord = self.cmp.compare(key, k);
+
+            if ord.is_eq() {
+                return self.inorder_successor(cur);
+            }
+
+            parent = cur;
+            cur = if ord.is_lt() {
+                unsafe { cur.as_ref().left }
+            } else {
+                unsafe { cur.as_ref().right }
+            };
+        }
+
+        // key is absent: `parent` is the node it would be attached to
+        if self.is_header(parent) {
+            return self.nil;
+        }
+        if self
+            .cmp
+            .compare(key, unsafe { parent.as_ref().key() }.borrow())
+            .is_lt()
+        {
+            parent
+        } else {
+            self.inorder_successor(parent)
+        }
+    }
+
+    /// Finds the node holding the largest key less than `key`, whether or not `key` is present.
+    fn predecessor_node<Q>(&self, key: &Q) -> NodePtr<K, V, A>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let mut parent = self.header;
+        let mut cur = unsafe { self.header.as_ref().right };
+
+        while !self.is_nil(cur) {
+            let k = unsafe { cur.as_ref().key().borrow() };
+            let ord = self.cmp.compare(key, k);
+
+            if ord.is_eq() {
+                return self.inorder_predecessor(cur);
+            }
+
+            parent = cur;
+            cur = if ord.is_lt() {
+                unsafe { cur.as_ref().left }
+            } else {
+                unsafe { cur.as_ref().right }
+            };
+        }
+
+        // key is absent: `parent` is the node it would be attached to
+        if self.is_header(parent) {
+            return self.nil;
+        }
+        if self
+            .cmp
+            .compare(key, unsafe { parent.as_ref().key() }.borrow())
+            .is_gt()
+        {
+            parent
+        } else {
+            self.inorder_predecessor(parent)
+        }
+    }
+
+    /// Inserts `key` with `value`, returning the old value if the key was already present.
+    ///
+    /// Each call searches from the root, so a `get` followed by an `insert` of the same key
+    /// walks the path twice; use `get_or_insert` or `entry` to do both in one descent.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old = match self.bs_insert(key, value) {
+            InsertResult::Old(old_value) => Some(old_value),
+            InsertResult::New(red_node) => {
+                self.insert_fixup(red_node);
+                self.len += 1;
+                None
+            }
+        };
+        self.debug_check();
+        old
+    }
+
+    /// Like [`RBTree::insert`], but returns an error instead of aborting if the new node can't
+    /// be allocated. On error the tree is left unchanged.
+    pub fn try_insert_alloc(&mut self, key: K, value: V) -> Result<Option<V>, TryReserveError> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Ok(Some(entry.insert(value))),
+            Entry::Vacant(VacantEntry {
+                key,
+                parent,
+                position,
+                ..
+            }) => {
+                // with a spare node ready, attaching doesn't allocate
+                self.try_reserve_node()?;
+                self.attach_node(parent, position, key, value);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Links a new node as the `position` child of `parent`, which must be nil there and be the
+    /// correct place for `key`, then rebalances. Returns the new node.
+    fn attach_node(
+        &mut self,
+        mut parent: NodePtr<K, V, A>,
+        position: NodePosition,
+        key: K,
+        value: V,
+    ) -> NodePtr<K, V, A> {
+        let mut node = self.new_node(key, value);
+        unsafe {
+            node.as_mut().parent = parent;
+            match position {
+                NodePosition::Left => parent.as_mut().left = node,
+                NodePosition::Right => parent.as_mut().right = node,
+            }
+        }
+        self.update_upward(node);
+        // fixup only recolors and rotates, so `node` keeps holding the new entry
+        self.insert_fixup(node);
+        self.len += 1;
+        self.debug_check();
+        node
+    }
+
+    /// Inserts like `insert`, but when `hint` is the key of the previous `insert_with_hint` and
+    /// `key` belongs right next to it, links the new node there without searching from the root.
+    /// Any other hint falls back to a normal insert, so feeding a nearly sorted stream with
+    /// each call's key as the next call's hint skips most of the comparisons.
+    pub fn insert_with_hint(&mut self, hint: Option<&K>, key: K, value: V) -> Option<V> {
+        let last = self.last_inserted;
+        if let Some(hint) = hint
+            && !self.is_nil(last)
+            && self
+                .cmp
+                .compare(unsafe { last.as_ref().key() }, hint)
+                .is_eq()
+        {
+            let ord = self.cmp.compare(&key, hint);
+            if ord.is_eq() {
+                let old = core::mem::replace(unsafe { (*last.as_ptr()).value_mut() }, value);
+                self.value_replaced(last);
+                return Some(old);
+            }
+
+            // `key` fits between `last` and its in-order neighbour on that side, so it goes in
+            // the nil child slot that sits between them: below `last` if that side is empty,
+            // otherwise below the neighbour, the extreme node of `last`'s subtree on that side
+            let slot = if ord.is_gt() {
+                let next = self.inorder_successor(last);
+                if self.is_nil(next)
+                    || self
+                        .cmp
+                        .compare(&key, unsafe { next.as_ref().key() })
+                        .is_lt()
+                {
+                    Some(if self.is_nil(unsafe { last.as_ref().right }) {
+                        (last, NodePosition::Right)
+                    } else {
+                        (next, NodePosition::Left)
+                    })
+                } else {
+                    None
+                }
+            } else {
+                let prev = self.inorder_predecessor(last);
+                if self.is_nil(prev)
+                    || self
+                        .cmp
+                        .compare(&key, unsafe { prev.as_ref().key() })
+                        .is_gt()
+                {
+                    Some(if self.is_nil(unsafe { last.as_ref().left }) {
+                        (last, NodePosition::Left)
+                    } else {
+                        (prev, NodePosition::Right)
+                    })
+                } else {
+                    None
+                }
+            };
+
+            if let Some((parent, position)) = slot {
+                self.last_inserted = self.attach_node(parent, position, key, value);
+                return None;
+            }
+        }
+
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let old = entry.insert(value);
+                self.last_inserted = entry.node;
+                Some(old)
+            }
+            Entry::Vacant(VacantEntry {
+                key,
+                parent,
+                position,
+                ..
+            }) => {
+                self.last_inserted = self.attach_node(parent, position, key, value);
+                None
+            }
+        }
+    }
+
+    /// Returns the value for `key`, inserting `f()` first if the key is absent. The flag is
+    /// `true` if the key already existed.
+    pub fn get_mut_or_insert_with<F: FnOnce() -> V>(
+        &mut self,
+        key: K,
+        f: F,
+    ) -> (ValueMut<'_, K, V, C, A>, bool) {
+        match self.entry(key) {
+            Entry::Occupied(entry) => (entry.into_mut(), true),
+            Entry::Vacant(entry) => (entry.insert(f()), false),
+        }
+    }
+
+    /// Like `from_sorted_iter`, for entries in ascending order under `cmp`.
+    pub(crate) fn from_sorted_iter_by<I: IntoIterator<Item = (K, V)>>(cmp: C, iter: I) -> Self {
+        let mut pairs: Vec<(K, V)> = Vec::new();
+        for (key, value) in iter {
+            if let Some(last) = pairs.last_mut() {
+                let ord = cmp.compare(&last.0, &key);
+                assert!(
+                    ord.is_le(),
+                    "from_sorted_iter: keys must be in ascending order"
+                );
+                if ord.is_eq() {
+                    last.1 = value;
+                    continue;
+                }
+            }
+            pairs.push((key, value));
+        }
+
+        let mut tree = Self::with_comparator(cmp);
+        let nodes: Vec<_> = pairs
+            .into_iter()
+            .map(|(key, value)| tree.new_node(key, value))
+            .collect();
+        let root = tree.link_balanced(&nodes);
+        unsafe { tree.header.as_mut().right = root };
+        tree.len = nodes.len();
+        tree
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>> RBTree<K, V, C, NoAugment> {
+    /// Returns mutable references to the values of two different keys at once, e.g. to update
+    /// an entry together with its successor. `None` if either key is missing or both name the
+    /// same entry.
+    ///
+    /// Only on trees without an [`Augment`]: the summaries above two values edited at once
+    /// could not be refreshed while either reference is alive.
+    pub fn get_pair_mut<Q>(&mut self, a: &Q, b: &Q) -> Option<(&mut V, &mut V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let mut node_a = self.search_node(a);
+        let mut node_b = self.search_node(b);
+        if self.is_nil(node_a) || self.is_nil(node_b) || node_a == node_b {
+            return None;
+        }
+
+        // distinct nodes, so the two references never alias
+        unsafe { Some((node_a.as_mut().value_mut(), node_b.as_mut().value_mut())) }
+    }
+}
+
+impl<K: Key, V: Value> RBTree<K, V> {
+    /// Builds a perfectly balanced tree from entries in ascending key order in O(n), without
+    /// any comparisons beyond checking the order. Runs of equal keys keep the last value.
+    ///
+    /// # Panics
+    ///
+    /// If a key is smaller than the one before it.
+    pub fn from_sorted_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self::from_sorted_iter_by(OrdComparator, iter)
+    }
+
+    /// Builds a balanced tree from entries in any order by sorting them first, which is
+    /// usually faster than inserting them one by one. Where a key repeats, the value that came
+    /// last in `pairs` wins, as with repeated `insert`s.
+    pub fn from_unsorted(mut pairs: Vec<(K, V)>) -> Self {
+        // stable, so equal keys keep their order and `from_sorted_iter` keeps the last one
+        pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        Self::from_sorted_iter(pairs)
+    }
+
+    /// Merges streams that are each in ascending key order into one balanced tree. Where streams
+    /// share a key, the value from the later stream wins.
+    pub fn from_sorted_merge<I>(iters: Vec<I>) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut streams: Vec<I::IntoIter> =
+            iters.into_iter().map(IntoIterator::into_iter).collect();
+        // the heap orders stream fronts by key, then by stream so later streams pop last on ties;
+        // each front's value waits in its stream's slot
+        let mut heads = BinaryHeap::with_capacity(streams.len());
+        let mut values: Vec<Option<V>> = Vec::with_capacity(streams.len());
+        for (i, stream) in streams.iter_mut().enumerate() {
+            values.push(None);
+            if let Some((key, value)) = stream.next() {
+                heads.push(Reverse((key, i)));
+                values[i] = Some(value);
+            }
+        }
+
+        Self::from_sorted_iter(core::iter::from_fn(|| {
+            let Reverse((key, i)) = heads.pop()?;
+            let value = values[i].take()?;
+            if let Some((next_key, next_value)) = streams[i].next() {
+                heads.push(Reverse((next_key, i)));
+                values[i] = Some(next_value);
+            }
+            Some((key, value))
+        }))
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Relinks all nodes into a perfectly balanced tree of minimal height, keeping every entry
+    /// in place. Useful as a safety net after bulk low-level mutation.
+    pub fn rebuild(&mut self) {
+        let mut nodes = Vec::with_capacity(self.len);
+        self.traverse(|node| nodes.push(node));
+
+        let root = self.link_balanced(&nodes);
+        unsafe { self.header.as_mut().right = root };
+        self.debug_check();
+    }
+
+    /// Links `nodes` (in ascending key order) into a balanced tree under the header and returns
+    /// its root.
+    fn link_balanced(&mut self, nodes: &[NodePtr<K, V, A>]) -> NodePtr<K, V, A> {
+        if nodes.is_empty() {
+            return self.nil;
+        }
+
+        // Splitting at the middle fills every level but the deepest one. Coloring that level red
+        // (unless it is the root) gives all paths the same black height.
+        let red_depth = nodes.len().ilog2() as usize;
+        self._link_balanced(nodes, self.header, 0, red_depth)
+    }
+
+    fn _link_balanced(
+        &mut self,
+        nodes: &[NodePtr<K, V, A>],
+        parent: NodePtr<K, V, A>,
+        depth: usize,
+        red_depth: usize,
+    ) -> NodePtr<K, V, A> {
+        if nodes.is_empty() {
+            return self.nil;
+        }
+
+        let mid = nodes.len() / 2;
+        let mut node = nodes[mid];
+        let left = self._link_balanced(&nodes[..mid], node, depth + 1, red_depth);
+        let right = self._link_balanced(&nodes[mid + 1..], node, depth + 1, red_depth);
+
+        unsafe {
+            let node_mut = node.as_mut();
+            node_mut.parent = parent;
+            node_mut.left = left;
+            node_mut.right = right;
+            node_mut.color = if depth == red_depth && depth > 0 {
+                Color::Red
+            } else {
+                Color::Black
+            };
         }
+        self.update_node(node);
+
+        node
     }
 
-    fn insert_fixup(&mut self, mut red_node: NodePtr<K, V>) {
+    fn insert_fixup(&mut self, mut red_node: NodePtr<K, V, A>) {
         let parent = unsafe { red_node.as_ref().parent };
         if self.is_header(parent) {
             unsafe { red_node.as_mut().color = Color::Black };
@@ -144,7 +1223,6 @@ impl<K: Key, V: Value> RBTree<K, V> {
         match unsafe { parent.as_ref() }.color {
             Color::Black => {
                 // if parent is black, done
-                return;
             }
             Color::Red => {
                 // if parent is red, resolve red-red conflict
@@ -222,9 +1300,9 @@ impl<K: Key, V: Value> RBTree<K, V> {
 
     fn insert_fixup_straight_line(
         &mut self,
-        red_child: NodePtr<K, V>,
-        red_p: NodePtr<K, V>,
-        black_g: NodePtr<K, V>,
+        red_child: NodePtr<K, V, A>,
+        red_p: NodePtr<K, V, A>,
+        black_g: NodePtr<K, V, A>,
         position: NodePosition,
     ) {
         assert_eq!(unsafe { red_child.as_ref() }.color, Color::Red);
@@ -247,43 +1325,450 @@ impl<K: Key, V: Value> RBTree<K, V> {
     pub fn remove(&mut self, key: &K) -> Option<V> {
         // println!("REMOVE::: {key}");
         // self.display();
-        let removed = self.bs_remove(key);
+        let node = self.search_node(key);
+        if self.is_nil(node) {
+            return None;
+        }
+
+        Some(self.remove_node(node).1)
+    }
+
+    /// Removes `key` and returns its value. Same as [`RBTree::remove`], named for set-like use.
+    pub fn take<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Removes `key` and returns the stored key together with its value.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let node = self.search_node(key);
+        if self.is_nil(node) {
+            return None;
+        }
+
+        Some(self.remove_node(node))
+    }
+
+    /// Removes the entry stored in `node` and rebalances, returning the entry.
+    ///
+    /// If `node` has two children, its in-order predecessor's entry is moved into it and the
+    /// predecessor's node is freed instead, so other pointers to `node` stay valid but may now
+    /// hold a different entry.
+    fn remove_node(&mut self, node: NodePtr<K, V, A>) -> (K, V) {
+        let removed = self.unlink_node(node);
+        self.len -= 1;
+        let entry = unsafe { Self::take_entry(removed) };
+        self.debug_check();
+        entry
+    }
+
+    /// Runs the check installed by `RBTreeBuilder::debug_validate`, if any, in debug builds.
+    /// Every method that changes the tree's links or length calls it before returning.
+    fn debug_check(&self) {
+        if cfg!(debug_assertions)
+            && let Some(check) = self.validate_hook
+        {
+            check(self);
+        }
+    }
+
+    /// Unlinks the entry stored in `node` and rebalances, like `remove_node`, but returns the
+    /// unlinked node instead of freeing it and leaves `len` alone.
+    fn unlink_node(&mut self, node: NodePtr<K, V, A>) -> NodePtr<K, V, A> {
+        let removed = self.bs_remove_node(node);
         // print!("removed:");
         // self.display_node(removed);
-        if self.is_nil(removed) {
-            return None;
+
+        // if removed node is red, just remove it
+        if unsafe { removed.as_ref().color } == Color::Black {
+            let double_black = unsafe {
+                let left = removed.as_ref().left;
+                let right = removed.as_ref().right;
+                if !self.is_nil(left) { left } else { right }
+            };
+            // print!("double black:");
+            // self.display_node(double_black);
+
+            self.remove_fixup(double_black, unsafe { removed.as_ref().parent });
         }
 
+        removed
+    }
+
+    /// Frees a node that is no longer linked into the tree, moving its entry out.
+    ///
+    /// Both key and value must be moved out and the summary dropped here: they are
+    /// `ManuallyDrop` or `MaybeUninit`, so freeing the node alone would leak them.
+    unsafe fn take_entry(node: NodePtr<K, V, A>) -> (K, V) {
         unsafe {
-            // if removed node is root or red, just remove it
-            if removed.as_ref().color == Color::Red {
-                let removed_box = Box::from_raw(removed.as_ptr());
-                let removed_node = *removed_box;
-                let value = ManuallyDrop::into_inner(removed_node.value.assume_init());
-                self.len -= 1;
-                return Some(value);
+            let mut node = *Box::from_raw(node.as_ptr());
+            node.summary.assume_init_drop();
+            (
+                ManuallyDrop::into_inner(node.key.assume_init()),
+                ManuallyDrop::into_inner(node.value.assume_init()),
+            )
+        }
+    }
+
+    /// Returns the smallest key, or `None` if the tree is empty.
+    pub fn min_key(&self) -> Option<&K> {
+        let first = self.first_node();
+        if self.is_nil(first) {
+            return None;
+        }
+        Some(unsafe { first.as_ref().key() })
+    }
+
+    /// Returns the largest key, or `None` if the tree is empty.
+    pub fn max_key(&self) -> Option<&K> {
+        let last = self.last_node();
+        if self.is_nil(last) {
+            return None;
+        }
+        Some(unsafe { last.as_ref().key() })
+    }
+
+    /// Returns the entry with the smallest key without removing it, for code using the tree as
+    /// a double-ended priority queue alongside `pop_first`.
+    pub fn peek_min(&self) -> Option<(&K, &V)> {
+        let first = self.first_node();
+        if self.is_nil(first) {
+            return None;
+        }
+        Some(unsafe { (first.as_ref().key(), first.as_ref().value()) })
+    }
+
+    /// Returns the entry with the largest key without removing it; the counterpart of
+    /// `pop_last`.
+    pub fn peek_max(&self) -> Option<(&K, &V)> {
+        let last = self.last_node();
+        if self.is_nil(last) {
+            return None;
+        }
+        Some(unsafe { (last.as_ref().key(), last.as_ref().value()) })
+    }
+
+    /// Returns the entry with the smallest key, with its value mutable in place.
+    #[allow(clippy::type_complexity)]
+    pub fn first_key_value_mut(&mut self) -> Option<(&K, ValueMut<'_, K, V, C, A>)> {
+        let first = self.first_node();
+        self.key_value_mut(first)
+    }
+
+    /// Returns the entry with the largest key, with its value mutable in place.
+    #[allow(clippy::type_complexity)]
+    pub fn last_key_value_mut(&mut self) -> Option<(&K, ValueMut<'_, K, V, C, A>)> {
+        let last = self.last_node();
+        self.key_value_mut(last)
+    }
+
+    /// Returns the `n` entries with the smallest keys, in ascending order. Walks only those
+    /// entries, so it costs O(log n + n) rather than a full traversal.
+    pub fn first_n(&self, n: usize) -> Vec<(&K, &V)> {
+        self.iter().take(n).collect()
+    }
+
+    /// Returns the `n` entries with the largest keys, in descending order.
+    pub fn last_n(&self, n: usize) -> Vec<(&K, &V)> {
+        self.range::<K, _>(..).rev().take(n).collect()
+    }
+
+    /// Removes and returns the entry with the smallest key.
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        let first = self.first_node();
+        if self.is_nil(first) {
+            return None;
+        }
+        Some(self.remove_node(first))
+    }
+
+    /// Removes and returns the entry with the largest key.
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        let last = self.last_node();
+        if self.is_nil(last) {
+            return None;
+        }
+        Some(self.remove_node(last))
+    }
+
+    /// Pops entries from the smallest key up for as long as `pred` holds for the current
+    /// smallest entry, e.g. every timer due by now. Returns them in ascending order.
+    pub fn pop_first_while<F: FnMut(&K, &V) -> bool>(&mut self, mut pred: F) -> Vec<(K, V)> {
+        let mut popped = Vec::new();
+        while let Some((key, value)) = self.peek_min()
+            && pred(key, value)
+        {
+            popped.extend(self.pop_first());
+        }
+        popped
+    }
+
+    /// Pops entries from the largest key down for as long as `pred` holds for the current
+    /// largest entry. Returns them in descending order.
+    pub fn pop_last_while<F: FnMut(&K, &V) -> bool>(&mut self, mut pred: F) -> Vec<(K, V)> {
+        let mut popped = Vec::new();
+        while let Some((key, value)) = self.peek_max()
+            && pred(key, value)
+        {
+            popped.extend(self.pop_last());
+        }
+        popped
+    }
+
+    /// Keeps the `len` smallest entries, dropping the rest from the largest down. Does nothing
+    /// if the tree has at most `len` entries.
+    pub fn truncate(&mut self, len: usize) {
+        while self.len > len {
+            self.pop_last();
+        }
+    }
+
+    /// Splits off the entry with the smallest key, returning it together with the rest of the tree.
+    pub fn split_first(mut self) -> Option<((K, V), Self)> {
+        let first = self.pop_first()?;
+        Some((first, self))
+    }
+
+    /// Splits off the entry with the largest key, returning it together with the rest of the tree.
+    pub fn split_last(mut self) -> Option<((K, V), Self)> {
+        let last = self.pop_last()?;
+        Some((last, self))
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.clear_with(|_, _| {});
+    }
+
+    /// Removes every entry but keeps the nodes in the spare pool instead of freeing them, so
+    /// `capacity()` is unchanged and refilling the tree to its previous length doesn't
+    /// allocate. `clear` frees the nodes.
+    ///
+    /// Like `clear_with`, the tree is emptied before any entry is dropped, so a panicking
+    /// `Drop` leaks the remaining entries rather than leaving them reachable.
+    pub fn clear_keep_capacity(&mut self) {
+        let mut spare = core::mem::take(&mut self.spare);
+        let first_cleared = spare.len();
+        spare.reserve_exact(self.len);
+        self.traverse(|node| spare.push(node));
+
+        unsafe { self.header.as_mut().right = self.nil };
+        self.len = 0;
+        self.last_inserted = self.nil;
+        self.spare = spare;
+
+        for i in first_cleared..self.spare.len() {
+            unsafe {
+                let node = self.spare[i].as_mut();
+                ManuallyDrop::drop(node.key.assume_init_mut());
+                ManuallyDrop::drop(node.value.assume_init_mut());
+                node.summary.assume_init_drop();
             }
         }
+        self.debug_check();
+    }
 
-        let double_black = unsafe {
-            let left = removed.as_ref().left;
-            let right = removed.as_ref().right;
-            if !self.is_nil(left) { left } else { right }
-        };
-        // print!("double black:");
-        // self.display_node(double_black);
+    /// Removes every entry, handing each one to `f` in ascending key order instead of dropping
+    /// it, e.g. to close handles stored as values.
+    ///
+    /// The tree is emptied before `f` first runs, so if `f` panics the undelivered entries are
+    /// leaked rather than left reachable.
+    pub fn clear_with<F: FnMut(K, V)>(&mut self, mut f: F) {
+        let mut nodes = Vec::with_capacity(self.len);
+        self.traverse(|node| nodes.push(node));
 
-        self.remove_fixup(double_black, unsafe { removed.as_ref().parent });
+        unsafe { self.header.as_mut().right = self.nil };
+        self.len = 0;
+        self.last_inserted = self.nil;
+        self.debug_check();
 
-        unsafe {
-            let removed_box = Box::from_raw(removed.as_ptr());
-            let value = ManuallyDrop::into_inner(removed_box.value.assume_init());
-            self.len -= 1;
-            Some(value)
+        for node in nodes {
+            let (key, value) = unsafe { Self::take_entry(node) };
+            f(key, value);
+        }
+    }
+
+    /// Consumes the tree, splitting it into the entries for which `f` returns `true` and the
+    /// rest.
+    pub fn partition<F: FnMut(&K, &V) -> bool>(self, mut f: F) -> (Self, Self)
+    where
+        C: Clone,
+    {
+        let mut matching = self.empty_like();
+        let mut non_matching = self.empty_like();
+        for (key, value) in self {
+            if f(&key, &value) {
+                matching.insert(key, value);
+            } else {
+                non_matching.insert(key, value);
+            }
+        }
+        (matching, non_matching)
+    }
+
+    /// The leftmost node, or nil if the tree is empty.
+    fn first_node(&self) -> NodePtr<K, V, A> {
+        let mut cur = unsafe { self.header.as_ref().right };
+        if self.is_nil(cur) {
+            return cur;
+        }
+        loop {
+            let left = unsafe { cur.as_ref().left };
+            if self.is_nil(left) {
+                return cur;
+            }
+            cur = left;
+        }
+    }
+
+    /// The rightmost node, or nil if the tree is empty.
+    fn last_node(&self) -> NodePtr<K, V, A> {
+        let mut cur = unsafe { self.header.as_ref().right };
+        if self.is_nil(cur) {
+            return cur;
+        }
+        loop {
+            let right = unsafe { cur.as_ref().right };
+            if self.is_nil(right) {
+                return cur;
+            }
+            cur = right;
+        }
+    }
+
+    /// Keeps only the entries for which `f` returns `true`, visiting them in ascending key order.
+    /// Returns the number of entries removed.
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) -> usize {
+        self.try_retain(|key, value| ControlFlow::Continue(f(key, value)))
+    }
+
+    /// Like `retain`, also passing each entry's 0-based position in key order (counting the
+    /// entries removed before it). Returns the number of entries removed.
+    pub fn retain_indexed<F: FnMut(usize, &K, &mut V) -> bool>(&mut self, mut f: F) -> usize {
+        let mut index = 0;
+        self.retain(|key, value| {
+            index += 1;
+            f(index - 1, key, value)
+        })
+    }
+
+    /// Visits entries in ascending key order, removing those for which `f` returns
+    /// `Continue(false)`. Returning `Break(())` stops the scan and leaves the remaining
+    /// entries untouched. Returns the number of entries removed. An `Augment` summary is
+    /// recomputed afterwards, so `f` can edit the values it keeps.
+    pub fn try_retain<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&K, &mut V) -> ControlFlow<(), bool>,
+    {
+        let mut to_remove = vec![];
+
+        let mut cur = self.inorder_successor(self.header);
+        while !self.is_nil(cur) {
+            let keep = unsafe {
+                let node = cur.as_mut();
+                f(node.key.assume_init_ref(), node.value.assume_init_mut())
+            };
+
+            match keep {
+                ControlFlow::Continue(true) => {}
+                ControlFlow::Continue(false) => to_remove.push(cur),
+                ControlFlow::Break(()) => break,
+            }
+
+            cur = self.inorder_successor(cur);
+        }
+
+        // `f` may have edited any value it saw; `remove_node` only refreshes the removed paths
+        self.refresh_summaries();
+
+        // Removing in ascending order is safe: `remove_node` only frees the in-order predecessor
+        // of the node it is given, which has already been handled.
+        let removed = to_remove.len();
+        for node in to_remove {
+            self.remove_node(node);
+        }
+        removed
+    }
+
+    /// Removes every entry whose value equals the value of the entry before it in key order,
+    /// keeping the first entry of each run, e.g. to run-length compress a sorted series.
+    /// Returns the number of entries removed.
+    pub fn dedup_adjacent_values(&mut self) -> usize
+    where
+        V: PartialEq,
+    {
+        let mut to_remove = vec![];
+
+        let mut prev = self.first_node();
+        if self.is_nil(prev) {
+            return 0;
+        }
+        let mut cur = self.inorder_successor(prev);
+        while !self.is_nil(cur) {
+            if unsafe { cur.as_ref().value() == prev.as_ref().value() } {
+                to_remove.push(cur);
+            }
+            prev = cur;
+            cur = self.inorder_successor(cur);
+        }
+
+        // ascending order, see `try_retain`
+        let removed = to_remove.len();
+        for node in to_remove {
+            self.remove_node(node);
         }
+        removed
     }
 
-    fn remove_fixup(&mut self, double_black: NodePtr<K, V>, parent: NodePtr<K, V>) {
+    /// Removes every entry whose key lies outside `range`, returning how many were removed.
+    /// Only the entries being removed are visited.
+    pub fn retain_range<Q, R>(&mut self, range: R) -> usize
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        R: RangeBounds<Q>,
+    {
+        let mut to_remove = vec![];
+
+        let mut cur = self.first_node();
+        while !self.is_nil(cur)
+            && bounds::before_start(&self.cmp, &range, unsafe { cur.as_ref().key() }.borrow())
+        {
+            to_remove.push(cur);
+            cur = self.inorder_successor(cur);
+        }
+
+        let below = to_remove.len();
+        let mut cur = self.last_node();
+        // stop at the nodes already collected, in case `range` is empty
+        while !self.is_nil(cur)
+            && to_remove[..below].last() != Some(&cur)
+            && bounds::after_end(&self.cmp, &range, unsafe { cur.as_ref().key() }.borrow())
+        {
+            to_remove.push(cur);
+            cur = self.inorder_predecessor(cur);
+        }
+        to_remove[below..].reverse();
+
+        // ascending order, see `try_retain`
+        let removed = to_remove.len();
+        for node in to_remove {
+            self.remove_node(node);
+        }
+        removed
+    }
+
+    fn remove_fixup(&mut self, double_black: NodePtr<K, V, A>, parent: NodePtr<K, V, A>) {
         // print!("remove fix up with double black: ");
         // unsafe {
         //     self.display_node(double_black);
@@ -331,7 +1816,11 @@ impl<K: Key, V: Value> RBTree<K, V> {
         }
     }
 
-    fn remove_fixup_black_sibling(&mut self, double_black: NodePtr<K, V>, parent: NodePtr<K, V>) {
+    fn remove_fixup_black_sibling(
+        &mut self,
+        double_black: NodePtr<K, V, A>,
+        parent: NodePtr<K, V, A>,
+    ) {
         let sibling = self.sibling_of_nil(parent, double_black);
 
         let (far_nephew, near_nephew) = unsafe {
@@ -372,10 +1861,10 @@ impl<K: Key, V: Value> RBTree<K, V> {
 
     fn remove_fixup_far_red_nephew(
         &mut self,
-        mut parent: NodePtr<K, V>,
-        mut sibling: NodePtr<K, V>,
-        double_black: NodePtr<K, V>,
-        far_nephew: NodePtr<K, V>,
+        mut parent: NodePtr<K, V, A>,
+        mut sibling: NodePtr<K, V, A>,
+        double_black: NodePtr<K, V, A>,
+        far_nephew: NodePtr<K, V, A>,
     ) {
         // case 1-2: if far nephew is red
         //   - rotate P, let S up
@@ -387,21 +1876,21 @@ impl<K: Key, V: Value> RBTree<K, V> {
             NodePosition::Right => self.rotate_left(parent),
         }
         unsafe {
-            std::mem::swap(&mut sibling.as_mut().color, &mut parent.as_mut().color);
+            core::mem::swap(&mut sibling.as_mut().color, &mut parent.as_mut().color);
         };
         self.color_black(double_black);
         self.color_black(far_nephew);
     }
 
     #[inline]
-    fn color_red(&mut self, mut node: NodePtr<K, V>) {
+    fn color_red(&mut self, mut node: NodePtr<K, V, A>) {
         unsafe {
             node.as_mut().color = Color::Red;
         };
     }
 
     #[inline]
-    fn color_black(&mut self, mut node: NodePtr<K, V>) {
+    fn color_black(&mut self, mut node: NodePtr<K, V, A>) {
         unsafe {
             node.as_mut().color = Color::Black;
         };
@@ -411,58 +1900,227 @@ impl<K: Key, V: Value> RBTree<K, V> {
     pub fn len(&self) -> usize {
         self.len
     }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
 }
 
-impl<K: Key + Debug, V: Value + Debug> RBTree<K, V> {
-    /// Prints the tree in a beautiful, human-readable format.
-    pub fn display(&self) {
-        println!("╔══════════════════════════════════════════════════════════════╗");
-        println!("║                        Red-Black Tree                        ║");
-        println!("╠══════════════════════════════════════════════════════════════╣");
+impl<K: Key, V: Value, C: Comparator<K> + Default, A: Augment<K, V>> Default
+    for RBTree<K, V, C, A>
+{
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
 
-        let root = unsafe { self.header.as_ref().right };
-        if self.is_nil(root) {
-            println!("║                        <EMPTY TREE>                         ║");
-            println!("╚═════════════════════════════════════════════════════════════╝");
+impl<K: Key, V: Value + PartialEq, C: Comparator<K>, A: Augment<K, V>> PartialEq
+    for RBTree<K, V, C, A>
+{
+    /// Trees are equal when they hold the same entries, whatever their shapes.
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: Key, V: Value + Eq, C: Comparator<K>, A: Augment<K, V>> Eq for RBTree<K, V, C, A> {}
+
+impl<K: Key, V: Value + PartialOrd, C: Comparator<K>, A: Augment<K, V>> PartialOrd
+    for RBTree<K, V, C, A>
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<K: Key, V: Value + Ord, C: Comparator<K>, A: Augment<K, V>> Ord for RBTree<K, V, C, A> {
+    /// Compares the entries in key order lexicographically, like `BTreeMap`: the first
+    /// differing key or value decides, and a tree that is a prefix of the other is smaller.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<K: Key + Clone, V: Value + Clone, C: Comparator<K> + Clone, A: Augment<K, V>> Clone
+    for RBTree<K, V, C, A>
+{
+    /// Copies the tree node by node, keeping its shape and colors, so no rebalancing is done.
+    ///
+    /// Each key and value is copied with its own `clone`, so the copy is only as deep as that:
+    /// `Rc`/`Arc` values end up shared between the two trees. For independent copies of shared
+    /// values, rebuild the tree with `iter().map(..)` and clone the inner data there.
+    fn clone(&self) -> Self {
+        let mut tree = self.copy_mapped(V::clone);
+        tree.validate_hook = self.validate_hook;
+        tree
+    }
+}
+
+impl<K: Key + Clone, V: Value, C: Comparator<K> + Clone, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Builds a tree with the same keys and `f` applied to each value. The nodes are copied
+    /// with their shape and colors, so the result is balanced exactly like `self` without any
+    /// rebalancing, in O(n). An `Augment` isn't carried over, since its value type differs.
+    pub fn map_values<W: Value, F: FnMut(&V) -> W>(&self, f: F) -> RBTree<K, W, C> {
+        self.copy_mapped(f)
+    }
+
+    /// Copies the tree's shape with `f` applied to each value, into a tree that keeps the
+    /// summaries of `B`.
+    fn copy_mapped<W: Value, B: Augment<K, W>, F: FnMut(&V) -> W>(
+        &self,
+        mut f: F,
+    ) -> RBTree<K, W, C, B> {
+        let mut tree = RBTree::with_comparator(self.cmp.clone());
+        let (root, header) = (unsafe { self.header.as_ref().right }, tree.header);
+        self.map_subtree(&mut tree, root, header, NodePosition::Right, &mut f);
+        tree.len = self.len;
+        tree
+    }
+
+    /// Builds a tree of the entries for which `f` returns `Some`, holding the returned values.
+    /// The survivors arrive in key order and are built into a balanced tree in O(n).
+    pub fn filter_map_values<W: Value, F: FnMut(&K, &V) -> Option<W>>(
+        &self,
+        mut f: F,
+    ) -> RBTree<K, W, C> {
+        RBTree::from_sorted_iter_by(
+            self.cmp.clone(),
+            self.iter()
+                .filter_map(|(key, value)| f(key, value).map(|value| (key.clone(), value))),
+        )
+    }
+
+    /// Copies the subtree rooted at `node` into `target` below `parent`, mapping each value
+    /// with `f`. Each copy is linked in before its children are copied, so a panicking `clone`
+    /// or `f` leaves nothing unreachable.
+    fn map_subtree<W: Value, B: Augment<K, W>, F: FnMut(&V) -> W>(
+        &self,
+        target: &mut RBTree<K, W, C, B>,
+        node: NodePtr<K, V, A>,
+        mut parent: NodePtr<K, W, B>,
+        position: NodePosition,
+        f: &mut F,
+    ) {
+        if self.is_nil(node) {
             return;
         }
 
-        // Count nodes for statistics
-        let node_count = self.count_nodes();
-        println!("║ Total nodes: {:<47} ║", node_count);
-        println!("║ Format: [key:value] (Color) [L/R]                            ║");
-        println!("║ Colors: 🔴Red  ⚫Black                                       ║");
-        println!("╚══════════════════════════════════════════════════════════════╝");
-        println!();
+        let node_ref = unsafe { node.as_ref() };
+        let mut copy = unsafe { target.new_node(node_ref.key().clone(), f(node_ref.value())) };
+        unsafe {
+            copy.as_mut().color = node_ref.color;
+            copy.as_mut().parent = parent;
+            match position {
+                NodePosition::Left => parent.as_mut().left = copy,
+                NodePosition::Right => parent.as_mut().right = copy,
+            }
+        }
 
-        let root_node = unsafe { root.as_ref() };
-        let color_symbol = match root_node.color {
-            Color::Red => "🔴",
-            Color::Black => "⚫",
-        };
+        self.map_subtree(target, node_ref.left, copy, NodePosition::Left, f);
+        self.map_subtree(target, node_ref.right, copy, NodePosition::Right, f);
+        // the children are in place, so the size and summary can be computed
+        target.update_node(copy);
+    }
+}
 
-        println!(
-            "{}[{:?}:{:?}] {} [ROOT]",
-            color_symbol,
-            unsafe { root_node.key() },
-            unsafe { root_node.value() },
-            color_symbol
-        );
+#[cfg(feature = "std")]
+impl<K: Key + Debug, V: Value + Debug, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Prints the tree in a beautiful, human-readable format.
+    pub fn display(&self) {
+        print!("{}", self.render_tree());
+    }
+
+    /// Prints the tree like `display`, with the header box at least `width` columns wide.
+    pub fn display_with_width(&self, width: usize) {
+        print!("{}", self.render_tree_with_width(width));
+    }
+
+    /// Returns the text `display` prints.
+    pub fn render_tree(&self) -> String {
+        self.render_tree_with_width(62)
+    }
+
+    /// Returns the text `display_with_width` prints. The box grows past `width` to fit the
+    /// widest line, so long keys or values don't break its alignment.
+    pub fn render_tree_with_width(&self, width: usize) -> String {
+        let root = unsafe { self.header.as_ref().right };
+        let mut lines = Vec::new();
+        if !self.is_nil(root) {
+            let root_node = unsafe { root.as_ref() };
+            let color_symbol = match root_node.color {
+                Color::Red => "🔴",
+                Color::Black => "⚫",
+            };
 
-        // Display children with proper positioning
-        if !self.is_nil(root_node.left) || !self.is_nil(root_node.right) {
-            self.display_subtree(root_node.left, root_node.right, "".to_string(), true);
+            lines.push(format!(
+                "{}[{:?}:{:?}] {} [ROOT]",
+                color_symbol,
+                unsafe { root_node.key() },
+                unsafe { root_node.value() },
+                color_symbol
+            ));
+
+            // Display children with proper positioning
+            if !self.is_nil(root_node.left) || !self.is_nil(root_node.right) {
+                self.display_subtree(
+                    root_node.left,
+                    root_node.right,
+                    "".to_string(),
+                    true,
+                    &mut lines,
+                );
+            }
         }
 
-        println!();
+        let header = if self.is_nil(root) {
+            vec!["<EMPTY TREE>".to_string()]
+        } else {
+            vec![
+                format!("Total nodes: {}", self.count_nodes()),
+                "Format: [key:value] (Color) [L/R]".to_string(),
+                "Colors: 🔴Red  ⚫Black".to_string(),
+            ]
+        };
+        let inner = header
+            .iter()
+            .map(|line| display_width(line) + 2)
+            .chain(lines.iter().map(|line| display_width(line)))
+            .fold(width, usize::max);
+
+        let bar = "═".repeat(inner);
+        let mut out = String::new();
+        let _ = writeln!(out, "╔{}╗", bar);
+        let _ = writeln!(out, "║{:^inner$}║", "Red-Black Tree");
+        let _ = writeln!(out, "╠{}╣", bar);
+        for line in &header {
+            let padding = inner - display_width(line) - 1;
+            if self.is_nil(root) {
+                let _ = writeln!(out, "║{:^inner$}║", line);
+            } else {
+                let _ = writeln!(out, "║ {}{}║", line, " ".repeat(padding));
+            }
+        }
+        let _ = writeln!(out, "╚{}╝", bar);
+        if self.is_nil(root) {
+            return out;
+        }
+
+        out.push('\n');
+        for line in &lines {
+            let _ = writeln!(out, "{}", line);
+        }
+        out.push('\n');
+        out
     }
 
     fn display_subtree(
         &self,
-        left: NodePtr<K, V>,
-        right: NodePtr<K, V>,
+        left: NodePtr<K, V, A>,
+        right: NodePtr<K, V, A>,
         prefix: String,
         is_root_level: bool,
+        lines: &mut Vec<String>,
     ) {
         let has_left = !self.is_nil(left);
         let has_right = !self.is_nil(right);
@@ -481,7 +2139,7 @@ impl<K: Key + Debug, V: Value + Debug> RBTree<K, V> {
                 Color::Black => "⚫",
             };
 
-            println!(
+            lines.push(format!(
                 "{}{}{}[{:?}:{:?}] {} [R]",
                 prefix,
                 connector,
@@ -489,19 +2147,15 @@ impl<K: Key + Debug, V: Value + Debug> RBTree<K, V> {
                 unsafe { right_node.key() },
                 unsafe { right_node.value() },
                 color_symbol
-            );
+            ));
 
             if !self.is_nil(right_node.left) || !self.is_nil(right_node.right) {
-                self.display_subtree(right_node.left, right_node.right, new_prefix, false);
+                self.display_subtree(right_node.left, right_node.right, new_prefix, false, lines);
             }
         }
 
         if has_left {
-            let new_prefix = if is_root_level {
-                format!("{}    ", prefix)
-            } else {
-                format!("{}    ", prefix)
-            };
+            let new_prefix = format!("{}    ", prefix);
 
             let left_node = unsafe { left.as_ref() };
             let color_symbol = match left_node.color {
@@ -509,17 +2163,17 @@ impl<K: Key + Debug, V: Value + Debug> RBTree<K, V> {
                 Color::Black => "⚫",
             };
 
-            println!(
+            lines.push(format!(
                 "{}└── {}[{:?}:{:?}] {} [L]",
                 prefix,
                 color_symbol,
                 unsafe { left_node.key() },
                 unsafe { left_node.value() },
                 color_symbol
-            );
+            ));
 
             if !self.is_nil(left_node.left) || !self.is_nil(left_node.right) {
-                self.display_subtree(left_node.left, left_node.right, new_prefix, false);
+                self.display_subtree(left_node.left, left_node.right, new_prefix, false, lines);
             }
         }
     }
@@ -536,7 +2190,7 @@ impl<K: Key + Debug, V: Value + Debug> RBTree<K, V> {
         println!();
     }
 
-    fn display_inorder(&self, node: NodePtr<K, V>) {
+    fn display_inorder(&self, node: NodePtr<K, V, A>) {
         if self.is_nil(node) {
             return;
         }
@@ -559,7 +2213,7 @@ impl<K: Key + Debug, V: Value + Debug> RBTree<K, V> {
     }
 
     #[allow(dead_code)]
-    fn display_node(&self, node: NodePtr<K, V>) {
+    fn display_node(&self, node: NodePtr<K, V, A>) {
         if self.is_nil(node) {
             println!("<nil>");
             return;
@@ -579,8 +2233,18 @@ impl<K: Key + Debug, V: Value + Debug> RBTree<K, V> {
     }
 }
 
-impl<K: Key + Display + Debug, V: Display + Debug> std::fmt::Display for RBTree<K, V> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// Terminal columns taken by `s`, counting the color emoji as two.
+#[cfg(feature = "std")]
+fn display_width(s: &str) -> usize {
+    s.chars()
+        .map(|c| if matches!(c, '🔴' | '⚫') { 2 } else { 1 })
+        .sum()
+}
+
+impl<K: Key + Display + Debug, V: Display + Debug, C: Comparator<K>, A: Augment<K, V>>
+    core::fmt::Display for RBTree<K, V, C, A>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let root = unsafe { self.header.as_ref().right };
         if self.is_nil(root) {
             write!(f, "RBTree(∅)")
@@ -592,12 +2256,14 @@ impl<K: Key + Display + Debug, V: Display + Debug> std::fmt::Display for RBTree<
     }
 }
 
-impl<K: Key + Display + Debug, V: Display + Debug> RBTree<K, V> {
+impl<K: Key + Display + Debug, V: Display + Debug, C: Comparator<K>, A: Augment<K, V>>
+    RBTree<K, V, C, A>
+{
     fn fmt_inorder(
         &self,
-        f: &mut std::fmt::Formatter<'_>,
-        node: NodePtr<K, V>,
-    ) -> std::fmt::Result {
+        f: &mut core::fmt::Formatter<'_>,
+        node: NodePtr<K, V, A>,
+    ) -> core::fmt::Result {
         if self.is_nil(node) {
             return Ok(());
         }
@@ -621,7 +2287,37 @@ impl<K: Key + Display + Debug, V: Display + Debug> RBTree<K, V> {
     }
 }
 
-impl<K: Key, V: Value> Drop for RBTree<K, V> {
+impl<K: Key + Debug, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Returns the exact shape of the tree in preorder as `(Color:Key left right)`, with `.` for
+    /// a nil child, e.g. `(B:10 (B:5 . (R:7 . .)) (B:15 . .))`. Handy for pinning the structure
+    /// in tests.
+    pub fn shape_string(&self) -> String {
+        let mut out = String::new();
+        self.write_shape(&mut out, unsafe { self.header.as_ref().right });
+        out
+    }
+
+    fn write_shape(&self, out: &mut String, node: NodePtr<K, V, A>) {
+        if self.is_nil(node) {
+            out.push('.');
+            return;
+        }
+
+        let node_ref = unsafe { node.as_ref() };
+        let color_char = match node_ref.color {
+            Color::Red => "R",
+            Color::Black => "B",
+        };
+        // writing into a `String` can't fail
+        let _ = write!(out, "({}:{:?} ", color_char, unsafe { node_ref.key() });
+        self.write_shape(out, node_ref.left);
+        out.push(' ');
+        self.write_shape(out, node_ref.right);
+        out.push(')');
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> Drop for RBTree<K, V, C, A> {
     fn drop(&mut self) {
         let mut nodes = vec![];
         self.traverse(|node| {
@@ -632,10 +2328,15 @@ impl<K: Key, V: Value> Drop for RBTree<K, V> {
                 let mut b = Box::from_raw(node.as_ptr()); // don't use * dereference because it requires a copy from heap to stack
                 ManuallyDrop::drop(b.key.assume_init_mut()); // just drop on heap
                 ManuallyDrop::drop(b.value.assume_init_mut());
+                b.summary.assume_init_drop();
                 drop(b);
             };
         }
 
+        for node in self.spare.drain(..) {
+            unsafe { drop(Box::from_raw(node.as_ptr())) };
+        }
+
         unsafe {
             drop(Box::from_raw(self.header.as_ptr()));
             drop(Box::from_raw(self.nil.as_ptr()));
@@ -643,5 +2344,15 @@ impl<K: Key, V: Value> Drop for RBTree<K, V> {
     }
 }
 
-unsafe impl<K: Key + Send, V: Value + Send> Send for RBTree<K, V> {}
-unsafe impl<K: Key + Sync, V: Value + Sync> Sync for RBTree<K, V> {}
+unsafe impl<K: Key + Send, V: Value + Send, C: Comparator<K> + Send, A: Augment<K, V>> Send
+    for RBTree<K, V, C, A>
+where
+    A::Summary: Send,
+{
+}
+unsafe impl<K: Key + Sync, V: Value + Sync, C: Comparator<K> + Sync, A: Augment<K, V>> Sync
+    for RBTree<K, V, C, A>
+where
+    A::Summary: Sync,
+{
+}
@@ -1,11 +1,13 @@
-use std::{
+use crate::augment::{Augment, NoAugment};
+use core::{
     fmt::Debug,
     mem::{ManuallyDrop, MaybeUninit},
     ptr::NonNull,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub(crate) enum Color {
+/// The color of a node, as reported by [`RBTree::traverse_detailed`](crate::RBTree::traverse_detailed).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
     Red,
     Black,
 }
@@ -16,19 +18,24 @@ impl<T> Key for T where T: Ord {}
 pub trait Value {}
 impl<T> Value for T {}
 
-pub(crate) type NodePtr<K, V> = NonNull<RBNode<K, V>>;
+pub(crate) type NodePtr<K, V, A = NoAugment> = NonNull<RBNode<K, V, A>>;
 
 #[derive(Debug)]
-pub struct RBNode<K: Key, V: Value> {
+pub struct RBNode<K: Key, V: Value, A: Augment<K, V> = NoAugment> {
     pub(crate) key: MaybeUninit<ManuallyDrop<K>>,
     pub(crate) value: MaybeUninit<ManuallyDrop<V>>,
+    /// The `Augment` summary of the subtree rooted here, uninitialized wherever the key and
+    /// value are
+    pub(crate) summary: MaybeUninit<A::Summary>,
     pub(crate) color: Color,
-    pub(crate) left: NodePtr<K, V>,
-    pub(crate) right: NodePtr<K, V>,
-    pub(crate) parent: NodePtr<K, V>,
+    /// Number of nodes in the subtree rooted here, 0 for the sentinels
+    pub(crate) size: usize,
+    pub(crate) left: NodePtr<K, V, A>,
+    pub(crate) right: NodePtr<K, V, A>,
+    pub(crate) parent: NodePtr<K, V, A>,
 }
 
-impl<K: Key, V: Value> RBNode<K, V> {
+impl<K: Key, V: Value, A: Augment<K, V>> RBNode<K, V, A> {
     pub(crate) unsafe fn key(&self) -> &K {
         unsafe { self.key.assume_init_ref() }
     }
@@ -45,4 +52,13 @@ impl<K: Key, V: Value> RBNode<K, V> {
     pub(crate) unsafe fn value_mut(&mut self) -> &mut V {
         unsafe { self.value.assume_init_mut() }
     }
+
+    pub(crate) unsafe fn summary(&self) -> &A::Summary {
+        unsafe { self.summary.assume_init_ref() }
+    }
+
+    /// Recomputes `size` from the children, whose sizes must already be right.
+    pub(crate) unsafe fn update_size(&mut self) {
+        self.size = unsafe { self.left.as_ref().size + self.right.as_ref().size } + 1;
+    }
 }
@@ -0,0 +1,100 @@
+use crate::{
+    Augment, Comparator, NoAugment, OrdComparator, RBTree,
+    binary_tree::BinaryTree,
+    node::{Color, Key, NodePtr, Value},
+};
+
+/// A read-only handle to a node of an [`RBTree`], for tools that need the tree's shape (colors
+/// and links) and not just its entries.
+pub struct NodeRef<
+    'a,
+    K: Key,
+    V: Value,
+    C: Comparator<K> = OrdComparator,
+    A: Augment<K, V> = NoAugment,
+> {
+    node: NodePtr<K, V, A>,
+    tree: &'a RBTree<K, V, C, A>,
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> Clone for NodeRef<'_, K, V, C, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> Copy for NodeRef<'_, K, V, C, A> {}
+
+impl<'a, K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> NodeRef<'a, K, V, C, A> {
+    pub fn key(&self) -> &'a K {
+        unsafe { self.node.as_ref().key() }
+    }
+
+    pub fn value(&self) -> &'a V {
+        unsafe { self.node.as_ref().value() }
+    }
+
+    /// The tree's [`Augment`] summary of the subtree rooted at this node.
+    pub fn summary(&self) -> &'a A::Summary {
+        unsafe { self.node.as_ref().summary() }
+    }
+
+    pub fn is_red(&self) -> bool {
+        unsafe { self.node.as_ref().color == Color::Red }
+    }
+
+    pub fn is_black(&self) -> bool {
+        !self.is_red()
+    }
+
+    /// The parent node, or `None` for the root.
+    pub fn parent(&self) -> Option<NodeRef<'a, K, V, C, A>> {
+        let parent = unsafe { self.node.as_ref().parent };
+        if self.tree.is_header(parent) {
+            return None;
+        }
+        self.wrap(parent)
+    }
+
+    pub fn left(&self) -> Option<NodeRef<'a, K, V, C, A>> {
+        self.wrap(unsafe { self.node.as_ref().left })
+    }
+
+    pub fn right(&self) -> Option<NodeRef<'a, K, V, C, A>> {
+        self.wrap(unsafe { self.node.as_ref().right })
+    }
+
+    fn wrap(&self, node: NodePtr<K, V, A>) -> Option<NodeRef<'a, K, V, C, A>> {
+        if self.tree.is_nil(node) {
+            return None;
+        }
+        Some(NodeRef {
+            node,
+            tree: self.tree,
+        })
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// The root node, or `None` if the tree is empty.
+    pub fn root(&self) -> Option<NodeRef<'_, K, V, C, A>> {
+        let root = unsafe { self.header.as_ref().right };
+        if self.is_nil(root) {
+            return None;
+        }
+        Some(NodeRef {
+            node: root,
+            tree: self,
+        })
+    }
+
+    /// Iterates over the nodes in key order.
+    pub fn node_iter(&self) -> impl Iterator<Item = NodeRef<'_, K, V, C, A>> {
+        let first = self.first_node();
+        core::iter::successors((!self.is_nil(first)).then_some(first), move |&node| {
+            let next = self.inorder_successor(node);
+            (!self.is_nil(next)).then_some(next)
+        })
+        .map(move |node| NodeRef { node, tree: self })
+    }
+}
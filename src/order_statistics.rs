@@ -0,0 +1,95 @@
+use core::borrow::Borrow;
+
+use crate::{
+    Augment, Comparator, RBTree, ValueMut,
+    node::{Key, NodePtr, Value},
+};
+
+// Every node stores the size of its subtree, which lets positional queries descend straight to
+// the answer instead of walking the entries in order.
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Returns the entry at in-order position `index` (0 is the smallest key) in O(log n).
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        let node = self.select_node(index);
+        if self.is_nil(node) {
+            return None;
+        }
+
+        unsafe { Some((node.as_ref().key(), node.as_ref().value())) }
+    }
+
+    /// Returns the entry at in-order position `index`, with a mutable value.
+    #[allow(clippy::type_complexity)]
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&K, ValueMut<'_, K, V, C, A>)> {
+        let node = self.select_node(index);
+        self.key_value_mut(node)
+    }
+
+    /// Removes and returns the entry at in-order position `index`.
+    pub fn remove_index(&mut self, index: usize) -> Option<(K, V)> {
+        let node = self.select_node(index);
+        if self.is_nil(node) {
+            return None;
+        }
+
+        Some(self.remove_node(node))
+    }
+
+    /// Returns the number of keys `k` with `low < k < high`, in O(log n) from the subtree
+    /// sizes rather than by walking the entries.
+    pub fn count_between<Q>(&self, low: &Q, high: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.count_below(high, false)
+            .saturating_sub(self.count_below(low, true))
+    }
+
+    /// Number of keys less than `key`, or less than or equal to it if `inclusive`.
+    fn count_below<Q>(&self, key: &Q, inclusive: bool) -> usize
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        let mut count = 0;
+        let mut cur = unsafe { self.header.as_ref().right };
+
+        while !self.is_nil(cur) {
+            let ord = self
+                .cmp
+                .compare(unsafe { cur.as_ref().key() }.borrow(), key);
+            if ord.is_lt() || (inclusive && ord.is_eq()) {
+                count += unsafe { cur.as_ref().left.as_ref().size } + 1;
+                cur = unsafe { cur.as_ref().right };
+            } else {
+                cur = unsafe { cur.as_ref().left };
+            }
+        }
+
+        count
+    }
+
+    /// Finds the node at in-order position `index`, or nil if `index >= len`.
+    pub(crate) fn select_node(&self, mut index: usize) -> NodePtr<K, V, A> {
+        let mut cur = unsafe { self.header.as_ref().right };
+
+        while !self.is_nil(cur) {
+            let left_size = unsafe { cur.as_ref().left.as_ref().size };
+
+            if index < left_size {
+                cur = unsafe { cur.as_ref().left };
+            } else if index == left_size {
+                return cur;
+            } else {
+                index -= left_size + 1;
+                cur = unsafe { cur.as_ref().right };
+            }
+        }
+
+        cur
+    }
+}
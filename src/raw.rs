@@ -0,0 +1,63 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::{ffi::c_void, marker::PhantomData, mem::ManuallyDrop, ptr::NonNull};
+
+use crate::{
+    Augment, NoAugment, OrdComparator, RBTree,
+    node::{Key, Value},
+};
+
+/// The parts of an [`RBTree`] released by [`RBTree::into_raw`], for keeping a tree behind a
+/// C FFI boundary. Turn it back into a tree with [`RBTree::from_raw`] to free it.
+#[derive(Debug)]
+pub struct RawRBTree<K: Key, V: Value, A: Augment<K, V> = NoAugment> {
+    /// Sentinel whose right child is the root
+    pub header: *mut c_void,
+    /// Shared black sentinel standing in for every missing child
+    pub nil: *mut c_void,
+    pub len: usize,
+    /// The key, value and `Augment` types of the tree, which the nodes are laid out for
+    pub marker: PhantomData<(K, V, A)>,
+}
+
+impl<K: Key, V: Value, A: Augment<K, V>> RBTree<K, V, OrdComparator, A> {
+    /// Consumes the tree without freeing it, returning its sentinels and length. Like
+    /// `Box::into_raw`, the nodes leak unless they are handed back to [`RBTree::from_raw`].
+    ///
+    /// Preallocated spare nodes are freed here, so the rebuilt tree starts without any, and a
+    /// `debug_validate` check is not carried over.
+    pub fn into_raw(mut self) -> RawRBTree<K, V, A> {
+        for node in core::mem::take(&mut self.spare) {
+            unsafe { drop(Box::from_raw(node.as_ptr())) };
+        }
+        let tree = ManuallyDrop::new(self);
+
+        RawRBTree {
+            header: tree.header.as_ptr().cast(),
+            nil: tree.nil.as_ptr().cast(),
+            len: tree.len,
+            marker: PhantomData,
+        }
+    }
+
+    /// Rebuilds a tree from the parts returned by [`RBTree::into_raw`].
+    ///
+    /// # Safety
+    ///
+    /// `raw` must come from `into_raw` on an `RBTree<K, V, OrdComparator, A>` with unchanged
+    /// fields, and must be passed to `from_raw` only once.
+    pub unsafe fn from_raw(raw: RawRBTree<K, V, A>) -> Self {
+        unsafe {
+            let nil = NonNull::new_unchecked(raw.nil.cast());
+            RBTree {
+                header: NonNull::new_unchecked(raw.header.cast()),
+                nil,
+                len: raw.len,
+                spare: Vec::new(),
+                last_inserted: nil,
+                rotations: 0,
+                cmp: OrdComparator,
+                validate_hook: None,
+            }
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use core::borrow::Borrow;
+
+use crate::{RBTree, node::Key};
+
+/// An ordered set backed by an [`RBTree`] with `()` values.
+#[derive(Debug)]
+pub struct RBSet<K: Key> {
+    tree: RBTree<K, ()>,
+}
+
+impl<K: Key> RBSet<K> {
+    pub fn new() -> Self {
+        Self {
+            tree: RBTree::new(),
+        }
+    }
+
+    /// Adds `key` to the set. Returns `false` if it was already present, in which case the
+    /// stored key is kept.
+    pub fn insert(&mut self, key: K) -> bool {
+        match self.tree.entry(key) {
+            crate::Entry::Occupied(_) => false,
+            crate::Entry::Vacant(entry) => {
+                entry.insert(());
+                true
+            }
+        }
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.get(key).is_some()
+    }
+
+    /// Removes `key`, returning whether it was present.
+    pub fn remove<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.remove_entry(key).is_some()
+    }
+
+    /// Removes `key` and returns the key that was stored in the set.
+    pub fn take<Q>(&mut self, key: &Q) -> Option<K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.remove_entry(key).map(|(key, _)| key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Iterates over the keys in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = &K> {
+        self.tree.iter().map(|(key, _)| key)
+    }
+}
+
+impl<K: Key> Default for RBSet<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
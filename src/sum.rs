@@ -0,0 +1,94 @@
+use core::{borrow::Borrow, iter::Sum, ops::RangeBounds};
+
+use crate::{
+    Augment, Comparator, OrdComparator, RBTree, bounds,
+    node::{Key, NodePtr},
+};
+
+/// Keeps the sum of the values in each subtree, so that [`RBTree::range_sum`] can add up whole
+/// subtrees at once.
+pub struct SumAugment;
+
+impl<K, T: Clone + Sum> Augment<K, T> for SumAugment {
+    type Summary = T;
+
+    fn summarize(_key: &K, value: &T, left: Option<&T>, right: Option<&T>) -> T {
+        left.cloned()
+            .into_iter()
+            .chain([value.clone()])
+            .chain(right.cloned())
+            .sum()
+    }
+}
+
+impl<K: Key, T: Clone + Sum> RBTree<K, T, OrdComparator, SumAugment> {
+    /// Creates an empty tree that maintains subtree sums for `range_sum`, through inserts,
+    /// removals, rotations and values edited through a [`ValueMut`](crate::ValueMut).
+    pub fn with_range_sum() -> Self {
+        RBTree::with_augment::<SumAugment>()
+    }
+}
+
+impl<K: Key, T: Clone + Sum, C: Comparator<K>> RBTree<K, T, C, SumAugment> {
+    /// Sums the values whose keys lie in `range` in O(log n), using the subtree sums kept by
+    /// [`SumAugment`].
+    pub fn range_sum<Q, R>(&self, range: R) -> T
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        R: RangeBounds<Q>,
+    {
+        let mut total = None;
+        let root = unsafe { self.header.as_ref().right };
+        self.range_sum_from(root, &range, true, true, &mut total);
+        total.unwrap_or_else(|| core::iter::empty().sum())
+    }
+
+    /// Adds the values in `range` below `node` to `total`. `check_start`/`check_end` say
+    /// whether the subtree can still hold keys outside the range on that side; once neither
+    /// can, the whole subtree's sum is taken.
+    fn range_sum_from<Q, R>(
+        &self,
+        node: NodePtr<K, T, SumAugment>,
+        range: &R,
+        check_start: bool,
+        check_end: bool,
+        total: &mut Option<T>,
+    ) where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+        R: RangeBounds<Q>,
+    {
+        if self.is_nil(node) {
+            return;
+        }
+
+        let node_ref = unsafe { node.as_ref() };
+        if !check_start && !check_end {
+            add(total, unsafe { node_ref.summary() }.clone());
+            return;
+        }
+
+        let key = unsafe { node_ref.key() }.borrow();
+        if check_start && bounds::before_start(&self.cmp, range, key) {
+            self.range_sum_from(node_ref.right, range, check_start, check_end, total);
+        } else if check_end && bounds::after_end(&self.cmp, range, key) {
+            self.range_sum_from(node_ref.left, range, check_start, check_end, total);
+        } else {
+            // everything left of an in-range key is below the end, everything right of it is
+            // past the start
+            self.range_sum_from(node_ref.left, range, check_start, false, total);
+            add(total, unsafe { node_ref.value() }.clone());
+            self.range_sum_from(node_ref.right, range, false, check_end, total);
+        }
+    }
+}
+
+fn add<T: Sum>(total: &mut Option<T>, value: T) {
+    *total = Some(match total.take() {
+        Some(total) => [total, value].into_iter().sum(),
+        None => value,
+    });
+}
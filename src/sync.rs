@@ -0,0 +1,82 @@
+use std::{borrow::Borrow, sync::RwLock};
+
+use crate::{
+    RBTree,
+    node::{Key, Value},
+};
+
+/// An [`RBTree`] behind a `RwLock`, for sharing between threads without handling guards.
+///
+/// Reads take the read lock, so they run concurrently with each other. All methods panic if
+/// the lock was poisoned by a panicking writer.
+#[derive(Debug)]
+pub struct RwRBTree<K: Key, V: Value> {
+    inner: RwLock<RBTree<K, V>>,
+}
+
+impl<K: Key, V: Value> RwRBTree<K, V> {
+    pub fn new() -> Self {
+        Self {
+            inner: RwLock::new(RBTree::new()),
+        }
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.inner.write().unwrap().insert(key, value)
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.inner.write().unwrap().take(key)
+    }
+
+    /// Returns a copy of the value for `key`, so no guard outlives the call.
+    pub fn get_cloned<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        V: Clone,
+    {
+        self.inner.read().unwrap().get(key).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Clones the whole tree under a single read lock, giving a consistent view that later
+    /// writes don't affect.
+    pub fn snapshot(&self) -> RBTree<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.inner.read().unwrap().clone()
+    }
+
+    /// Unwraps the inner tree.
+    pub fn into_inner(self) -> RBTree<K, V> {
+        self.inner.into_inner().unwrap()
+    }
+}
+
+impl<K: Key, V: Value> Default for RwRBTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Key, V: Value> From<RBTree<K, V>> for RwRBTree<K, V> {
+    fn from(tree: RBTree<K, V>) -> Self {
+        Self {
+            inner: RwLock::new(tree),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use crate::{
+    Augment, Comparator, RBTree,
+    node::{Key, Value},
+};
+
+impl<
+    K: Key + Clone + Debug,
+    V: Value + Clone + Debug + PartialEq,
+    C: Comparator<K>,
+    A: Augment<K, V>,
+> RBTree<K, V, C, A>
+{
+    /// Asserts that the tree holds exactly `expected` and passes `validate()`, panicking with
+    /// the first difference otherwise. `expected` may be in any order; a key given more than once
+    /// keeps its last value, as repeated inserts would.
+    ///
+    /// Meant for differential fuzzing against another map; enabled by the `testing` feature.
+    pub fn assert_matches<I: IntoIterator<Item = (K, V)>>(&self, expected: I) {
+        if let Err(e) = self.validate() {
+            panic!("tree is invalid: {:?}", e);
+        }
+
+        let mut expected: Vec<(K, V)> = expected.into_iter().collect();
+        // stable, so equal keys stay in input order and the last one is kept below
+        expected.sort_by(|a, b| self.cmp.compare(&a.0, &b.0));
+        let mut deduped: Vec<(K, V)> = Vec::with_capacity(expected.len());
+        for (key, value) in expected {
+            match deduped.last_mut() {
+                Some(last) if self.cmp.compare(&last.0, &key).is_eq() => last.1 = value,
+                _ => deduped.push((key, value)),
+            }
+        }
+
+        assert_eq!(
+            self.len(),
+            deduped.len(),
+            "tree has {} entries, expected {}",
+            self.len(),
+            deduped.len()
+        );
+        for (i, ((key, value), (expected_key, expected_value))) in
+            self.iter().zip(&deduped).enumerate()
+        {
+            assert!(
+                self.cmp.compare(key, expected_key).is_eq() && value == expected_value,
+                "entry {} is ({:?}, {:?}), expected ({:?}, {:?})",
+                i,
+                key,
+                value,
+                expected_key,
+                expected_value
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RBTree;
+
+    #[test]
+    fn test_assert_matches() {
+        let mut tree = RBTree::new();
+        for i in (0..100).rev() {
+            tree.insert(i, i * 2);
+        }
+        tree.remove(&50);
+
+        let mut expected: Vec<_> = (0..100).filter(|&i| i != 50).map(|i| (i, i * 2)).collect();
+        expected.reverse();
+        tree.assert_matches(expected.clone());
+
+        // the last value for a repeated key wins
+        expected.push((7, 0));
+        expected.push((7, 14));
+        tree.assert_matches(expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "entry 3 is (3, 6), expected (3, 7)")]
+    fn test_assert_matches_reports_value_mismatch() {
+        let tree: RBTree<_, _> = (0..5).map(|i| (i, i * 2)).collect();
+        tree.assert_matches([(0, 0), (1, 2), (2, 4), (3, 7), (4, 8)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "tree has 5 entries, expected 4")]
+    fn test_assert_matches_reports_len_mismatch() {
+        let tree: RBTree<_, _> = (0..5).map(|i| (i, i)).collect();
+        tree.assert_matches((0..4).map(|i| (i, i)));
+    }
+}
@@ -1,7 +1,8 @@
-use std::fmt::{Debug, Display};
+use alloc::{format, string::String, vec::Vec};
+use core::fmt::{Debug, Display};
 
 use crate::{
-    RBTree,
+    Augment, Comparator, RBTree,
     binary_search_tree::validate::BSTValidator,
     node::{Color, Key, NodePtr, Value},
 };
@@ -18,12 +19,22 @@ pub enum RBTreeError<K: Key> {
         left_b_height: usize,
         right_b_height: usize,
     },
+    /// the `header` or `nil` sentinel is not black, or the root's parent is not `header`
+    SentinelCorrupted { message: &'static str },
     /// BST property violation
     BSTViolation { message: String },
+    /// the stored `len` differs from the number of nodes in the tree
+    LenMismatch { counted: usize, stored: usize },
+    /// a node's stored subtree size differs from the number of nodes below it
+    SizeMismatch {
+        node: K,
+        counted: usize,
+        stored: usize,
+    },
 }
 
 impl<K: Key + Display> Display for RBTreeError<K> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             RBTreeError::RootNotBlack { root } => {
                 write!(
@@ -50,20 +61,242 @@ impl<K: Key + Display> Display for RBTreeError<K> {
                     node, left_b_height, right_b_height
                 )
             }
+            RBTreeError::SentinelCorrupted { message } => {
+                write!(f, "Red-Black Tree validation failed: {}", message)
+            }
             RBTreeError::BSTViolation { message } => {
                 write!(f, "Binary Search Tree validation failed: {}", message)
             }
+            RBTreeError::LenMismatch { counted, stored } => {
+                write!(
+                    f,
+                    "Red-Black Tree validation failed: len is {} but the tree has {} nodes",
+                    stored, counted
+                )
+            }
+            RBTreeError::SizeMismatch {
+                node,
+                counted,
+                stored,
+            } => {
+                write!(
+                    f,
+                    "Red-Black Tree validation failed: node '{}' stores subtree size {} but has {} nodes",
+                    node, stored, counted
+                )
+            }
         }
     }
 }
 
-impl<K: Key + Clone + Debug, V: Value + Clone> RBTree<K, V> {
+impl<K: Key + Clone + Debug, V: Value + Clone, C: Comparator<K>, A: Augment<K, V>>
+    RBTree<K, V, C, A>
+{
+    /// Checks the binary-search-tree ordering, the red-black properties and the stored sizes.
+    ///
+    /// Keys are compared with the tree's comparator, the same ordering every lookup uses, so a
+    /// tree built with a descending `RBTreeBuilder::comparator` validates like any other.
     pub fn validate(&self) -> Result<(), RBTreeError<K>> {
+        // The sentinels come first: every other check, and the black-height count in particular
+        // (which counts nil as black), relies on them.
+        self.validate_sentinels()?;
+
         // First validate BST properties using the trait
         if let Err(bst_error) = BSTValidator::validate_bst(self) {
             return Err(RBTreeError::BSTViolation { message: bst_error });
         }
 
+        let counted = self.count_nodes();
+        if counted != self.len {
+            return Err(RBTreeError::LenMismatch {
+                counted,
+                stored: self.len,
+            });
+        }
+
+        // properties 2, 4 & 5
+        self.validate_rb_only()?;
+
+        // order statistics: subtree sizes
+        let root = unsafe { self.header.as_ref().right };
+        if !self.is_nil(root) {
+            self.validate_sizes(root)?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_sentinels(&self) -> Result<(), RBTreeError<K>> {
+        self.check_sentinels()
+            .map_err(|message| RBTreeError::SentinelCorrupted { message })
+    }
+
+    /// Runs `validate` and panics with the error if the tree is invalid, as a one-line check
+    /// for tests and examples.
+    #[track_caller]
+    pub fn assert_valid(&self) {
+        if let Err(e) = self.validate() {
+            panic!("tree invalid: {:?}", e);
+        }
+    }
+
+    /// Checks only the red-black coloring rules: black sentinels and root, no red node with a
+    /// red child and equal black heights. It skips the ordering, structure, cycle and size
+    /// checks of `validate`, so it assumes the links are sound; cheap enough to call after
+    /// every step of a fuzz loop, with a full `validate` now and then.
+    pub fn validate_rb_only(&self) -> Result<(), RBTreeError<K>> {
+        self.check_coloring().map_err(Self::coloring_error)
+    }
+
+    /// Like `validate`, but on failure the message also carries `shape_string()`, so a failing
+    /// fuzz or property test shows the offending structure rather than just the violation.
+    /// The shape is left out when the links are inconsistent or form a cycle, since walking
+    /// them might not terminate.
+    pub fn validate_dbg(&self) -> Result<(), String> {
+        self.validate().map_err(|error| {
+            // the cycle check counts the nodes, which is only safe on consistent links
+            if self.validate_structure().is_ok() && self.validate_no_cycles().is_ok() {
+                format!("{:?}\nshape: {}", error, self.shape_string())
+            } else {
+                format!("{:?}", error)
+            }
+        })
+    }
+
+    /// Names the nodes of a broken coloring rule by their keys.
+    fn coloring_error(error: ColoringError<K, V, A>) -> RBTreeError<K> {
+        let key = |node: NodePtr<K, V, A>| unsafe { node.as_ref().key() }.clone();
+        match error {
+            ColoringError::Sentinel(message) => RBTreeError::SentinelCorrupted { message },
+            ColoringError::RootNotBlack(root) => RBTreeError::RootNotBlack { root: key(root) },
+            ColoringError::RedParentRedChild { parent, child } => RBTreeError::RedParentRedChild {
+                parent: key(parent),
+                child: key(child),
+            },
+            ColoringError::BlackHeightMismatch {
+                node,
+                left_b_height,
+                right_b_height,
+            } => RBTreeError::BlackHeightMismatch {
+                node: key(node),
+                left_b_height,
+                right_b_height,
+            },
+        }
+    }
+
+    /// Checks every node's stored subtree size, returning the size of the subtree at `node`.
+    fn validate_sizes(&self, node: NodePtr<K, V, A>) -> Result<usize, RBTreeError<K>> {
+        if self.is_nil(node) {
+            return Ok(0);
+        }
+
+        let node_ref = unsafe { node.as_ref() };
+        let counted =
+            self.validate_sizes(node_ref.left)? + self.validate_sizes(node_ref.right)? + 1;
+        if counted != node_ref.size {
+            return Err(RBTreeError::SizeMismatch {
+                node: unsafe { node_ref.key() }.clone(),
+                counted,
+                stored: node_ref.size,
+            });
+        }
+
+        Ok(counted)
+    }
+}
+
+impl<K: Key + Debug, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Checks the red-black properties like `validate`, but keeps going after the first problem
+    /// and reports every violation together with the keys on the path from the root to it,
+    /// e.g. `black height mismatch (left: 2, right: 1) at path [10, 5]`.
+    pub fn validate_verbose(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        let root = unsafe { self.header.as_ref().right };
+        if !self.is_nil(root) && unsafe { root.as_ref() }.color == Color::Red {
+            errors.push(format!("root is not black at path [{:?}]", unsafe {
+                root.as_ref().key()
+            }));
+        }
+
+        let mut path = Vec::new();
+        self.verbose_subtree(root, &mut path, &mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Collects the violations below `node` and returns its black height, taking the left
+    /// side's where the two sides disagree so checking can continue above.
+    fn verbose_subtree<'a>(
+        &'a self,
+        node: NodePtr<K, V, A>,
+        path: &mut Vec<&'a K>,
+        errors: &mut Vec<String>,
+    ) -> usize {
+        if self.is_nil(node) {
+            return 1;
+        }
+
+        let node_ref = unsafe { node.as_ref() };
+        path.push(unsafe { node_ref.key() });
+
+        if node_ref.color == Color::Red {
+            for child in [node_ref.left, node_ref.right] {
+                if !self.is_nil(child) && unsafe { child.as_ref() }.color == Color::Red {
+                    errors.push(format!(
+                        "red node has red child {:?} at path {:?}",
+                        unsafe { child.as_ref().key() },
+                        path
+                    ));
+                }
+            }
+        }
+
+        let left_b_height = self.verbose_subtree(node_ref.left, path, errors);
+        let right_b_height = self.verbose_subtree(node_ref.right, path, errors);
+        if left_b_height != right_b_height {
+            errors.push(format!(
+                "black height mismatch (left: {}, right: {}) at path {:?}",
+                left_b_height, right_b_height, path
+            ));
+        }
+
+        path.pop();
+        left_b_height + if node_ref.color == Color::Black { 1 } else { 0 }
+    }
+}
+
+impl<K: Key, V: Value, C: Comparator<K>, A: Augment<K, V>> RBTree<K, V, C, A> {
+    /// Returns whether the coloring rules hold: black sentinels and root, no red node with a
+    /// red child and equal black heights. The same checks as `validate_rb_only`, answered with
+    /// a plain `bool`, so `K` needs no `Clone` or `Debug` and nothing is allocated.
+    pub fn is_balanced(&self) -> bool {
+        self.check_coloring().is_ok()
+    }
+
+    /// Checks that both sentinels are black and that the root hangs off `header`.
+    fn check_sentinels(&self) -> Result<(), &'static str> {
+        if unsafe { self.header.as_ref() }.color != Color::Black {
+            return Err("header sentinel is not black");
+        }
+        if unsafe { self.nil.as_ref() }.color != Color::Black {
+            return Err("nil sentinel is not black");
+        }
+
+        let root = unsafe { self.header.as_ref().right };
+        if !self.is_nil(root) && unsafe { root.as_ref() }.parent != self.header {
+            return Err("root's parent is not the header sentinel");
+        }
+        Ok(())
+    }
+
+    /// The checks behind `validate_rb_only` and `is_balanced`.
+    fn check_coloring(&self) -> Result<(), ColoringError<K, V, A>> {
+        self.check_sentinels().map_err(ColoringError::Sentinel)?;
         let root = unsafe { self.header.as_ref().right };
         if self.is_nil(root) {
             return Ok(());
@@ -71,18 +304,15 @@ impl<K: Key + Clone + Debug, V: Value + Clone> RBTree<K, V> {
 
         // property 2: root is black
         if unsafe { root.as_ref() }.color == Color::Red {
-            return Err(RBTreeError::RootNotBlack {
-                root: unsafe { root.as_ref().key() }.clone(),
-            });
+            return Err(ColoringError::RootNotBlack(root));
         }
 
         // property 4 & 5
-        self.validate_subtree(root)?;
-
-        Ok(())
+        self.check_subtree(root).map(|_| ())
     }
 
-    fn validate_subtree(&self, node: NodePtr<K, V>) -> Result<usize, RBTreeError<K>> {
+    /// Black height of the subtree at `node`, or the first coloring rule it breaks.
+    fn check_subtree(&self, node: NodePtr<K, V, A>) -> Result<usize, ColoringError<K, V, A>> {
         if self.is_nil(node) {
             return Ok(1); // black height of nil is 1
         }
@@ -91,36 +321,291 @@ impl<K: Key + Clone + Debug, V: Value + Clone> RBTree<K, V> {
 
         // property 4: red node cannot have red children
         if node_ref.color == Color::Red {
-            let left_child = unsafe { node_ref.left.as_ref() };
-            if left_child.color == Color::Red {
-                return Err(RBTreeError::RedParentRedChild {
-                    parent: unsafe { node_ref.key() }.clone(),
-                    child: unsafe { left_child.key() }.clone(),
-                });
-            }
-
-            let right_child = unsafe { node_ref.right.as_ref() };
-            if right_child.color == Color::Red {
-                return Err(RBTreeError::RedParentRedChild {
-                    parent: unsafe { node_ref.key() }.clone(),
-                    child: unsafe { right_child.key() }.clone(),
-                });
+            for child in [node_ref.left, node_ref.right] {
+                if unsafe { child.as_ref() }.color == Color::Red {
+                    return Err(ColoringError::RedParentRedChild {
+                        parent: node,
+                        child,
+                    });
+                }
             }
         }
 
-        let left_b_height = self.validate_subtree(node_ref.left)?;
-        let right_b_height = self.validate_subtree(node_ref.right)?;
+        let left_b_height = self.check_subtree(node_ref.left)?;
+        let right_b_height = self.check_subtree(node_ref.right)?;
 
         // property 5: black height must be same for all paths
         if left_b_height != right_b_height {
-            return Err(RBTreeError::BlackHeightMismatch {
-                node: unsafe { node_ref.key() }.clone(),
+            return Err(ColoringError::BlackHeightMismatch {
+                node,
                 left_b_height,
                 right_b_height,
             });
         }
 
-        let self_b_height = left_b_height + if node_ref.color == Color::Black { 1 } else { 0 };
-        Ok(self_b_height)
+        Ok(left_b_height + usize::from(node_ref.color == Color::Black))
+    }
+}
+
+/// A broken coloring rule, pointing at the nodes involved so that finding it needs nothing
+/// from `K`. `validate_rb_only` turns it into an [`RBTreeError`] holding their keys.
+enum ColoringError<K: Key, V: Value, A: Augment<K, V>> {
+    Sentinel(&'static str),
+    RootNotBlack(NodePtr<K, V, A>),
+    RedParentRedChild {
+        parent: NodePtr<K, V, A>,
+        child: NodePtr<K, V, A>,
+    },
+    BlackHeightMismatch {
+        node: NodePtr<K, V, A>,
+        left_b_height: usize,
+        right_b_height: usize,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RBTreeError;
+    use crate::RBTree;
+
+    fn setup_tree() -> RBTree<i32, &'static str> {
+        let mut tree = RBTree::new();
+        tree.insert(10, "ten");
+        tree.insert(5, "five");
+        tree.insert(15, "fifteen");
+        tree.insert(3, "three");
+        tree
+    }
+
+    #[test]
+    fn test_len_mismatch_is_caught() {
+        let mut tree = setup_tree();
+        assert_eq!(tree.validate(), Ok(()));
+
+        tree.len += 1;
+        assert_eq!(
+            tree.validate(),
+            Err(RBTreeError::LenMismatch {
+                counted: 4,
+                stored: 5
+            })
+        );
+
+        // restore it so that dropping the tree stays consistent
+        tree.len -= 1;
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn test_debug_validate_runs_after_bulk_changes() {
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        let bulk: [fn(&mut RBTree<i32, i32>); 4] = [
+            |tree| {
+                tree.remove_range_fast(100..200);
+            },
+            |tree| tree.extend_sorted([(100, 100), (101, 101)]),
+            |tree| {
+                tree.split_off_after(&50);
+            },
+            |tree| tree.rebuild(),
+        ];
+        for change in bulk {
+            let mut tree = RBTree::builder().debug_validate().build();
+            for i in 0..64 {
+                tree.insert(i, i);
+            }
+
+            // none of the changes touch this, so only a full check notices it
+            tree.len += 1;
+            assert!(catch_unwind(AssertUnwindSafe(|| change(&mut tree))).is_err());
+            tree.len -= 1;
+        }
+    }
+
+    #[test]
+    fn test_size_mismatch_is_caught() {
+        let tree = setup_tree();
+        let mut root = unsafe { tree.header.as_ref().right };
+
+        unsafe { root.as_mut().size += 1 };
+        assert_eq!(
+            tree.validate(),
+            Err(RBTreeError::SizeMismatch {
+                node: 10,
+                counted: 4,
+                stored: 5
+            })
+        );
+
+        unsafe { root.as_mut().size -= 1 };
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_verbose_reports_paths() {
+        let tree = setup_tree();
+        assert_eq!(tree.validate_verbose(), Ok(()));
+
+        // (B:10 (B:5 (R:3 . .) .) (B:15 . .)): blackening 3 breaks the black height below 5
+        let root = unsafe { tree.header.as_ref().right };
+        let mut node_3 = unsafe { root.as_ref().left.as_ref().left };
+        unsafe { node_3.as_mut().color = crate::node::Color::Black };
+        assert_eq!(
+            tree.validate_verbose(),
+            Err(vec![
+                "black height mismatch (left: 2, right: 1) at path [10, 5]".to_string(),
+                "black height mismatch (left: 3, right: 2) at path [10]".to_string(),
+            ])
+        );
+
+        // a red-red violation is reported alongside
+        let mut node_5 = unsafe { root.as_ref().left };
+        unsafe {
+            node_3.as_mut().color = crate::node::Color::Red;
+            node_5.as_mut().color = crate::node::Color::Red;
+        }
+        let errors = tree.validate_verbose().unwrap_err();
+        assert!(errors.contains(&"red node has red child 3 at path [10, 5]".to_string()));
+
+        unsafe { node_5.as_mut().color = crate::node::Color::Black };
+        assert_eq!(tree.validate_verbose(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_dbg_includes_shape() {
+        let tree = setup_tree();
+        assert_eq!(tree.validate_dbg(), Ok(()));
+
+        let root = unsafe { tree.header.as_ref().right };
+        let mut node_3 = unsafe { root.as_ref().left.as_ref().left };
+        unsafe { node_3.as_mut().color = crate::node::Color::Black };
+        let error = tree.validate_dbg().unwrap_err();
+        assert!(error.starts_with("BlackHeightMismatch"));
+        assert!(error.ends_with("shape: (B:10 (B:5 (B:3 . .) .) (B:15 . .))"));
+        unsafe { node_3.as_mut().color = crate::node::Color::Red };
+    }
+
+    #[test]
+    fn test_validate_dbg_leaves_out_shape_of_cycle() {
+        let tree = setup_tree();
+        let root = unsafe { tree.header.as_ref().right };
+        let mut node_3 = unsafe { root.as_ref().left.as_ref().left };
+
+        // linking the root below 3 closes a cycle that a walk would never leave
+        unsafe { node_3.as_mut().left = root };
+        let error = tree.validate_dbg().unwrap_err();
+        assert!(error.starts_with("BSTViolation"));
+        assert!(!error.contains("shape:"));
+
+        unsafe { node_3.as_mut().left = tree.nil };
+        assert_eq!(tree.validate_dbg(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_rb_only_catches_coloring() {
+        let tree = setup_tree();
+        let root = unsafe { tree.header.as_ref().right };
+        let mut node_3 = unsafe { root.as_ref().left.as_ref().left };
+        unsafe { node_3.as_mut().color = crate::node::Color::Black };
+
+        assert!(tree.validate_rb_only().is_err());
+        assert_eq!(tree.validate_rb_only(), tree.validate());
+        unsafe { node_3.as_mut().color = crate::node::Color::Red };
+        assert_eq!(tree.validate_rb_only(), Ok(()));
+    }
+
+    #[test]
+    fn test_is_balanced_catches_coloring() {
+        let tree = setup_tree();
+        assert!(tree.is_balanced());
+
+        let mut root = unsafe { tree.header.as_ref().right };
+        let mut node_3 = unsafe { root.as_ref().left.as_ref().left };
+        unsafe { node_3.as_mut().color = crate::node::Color::Black };
+        assert!(!tree.is_balanced());
+        unsafe { node_3.as_mut().color = crate::node::Color::Red };
+
+        // red-red between 5 and 3
+        let mut node_5 = unsafe { root.as_ref().left };
+        unsafe { node_5.as_mut().color = crate::node::Color::Red };
+        assert!(!tree.is_balanced());
+        unsafe { node_5.as_mut().color = crate::node::Color::Black };
+
+        unsafe { root.as_mut().color = crate::node::Color::Red };
+        assert!(!tree.is_balanced());
+        unsafe { root.as_mut().color = crate::node::Color::Black };
+        assert!(tree.is_balanced());
+    }
+
+    #[test]
+    fn test_assert_valid_passes_on_valid_tree() {
+        setup_tree().assert_valid();
+    }
+
+    #[test]
+    #[should_panic(expected = "tree invalid: BlackHeightMismatch")]
+    fn test_assert_valid_panics_on_invalid_tree() {
+        let tree = setup_tree();
+        let root = unsafe { tree.header.as_ref().right };
+        let mut node_3 = unsafe { root.as_ref().left.as_ref().left };
+        unsafe { node_3.as_mut().color = crate::node::Color::Black };
+        tree.assert_valid();
+    }
+
+    // the check only runs in builds with debug assertions
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "tree invariant broken by the last mutation: BlackHeightMismatch")]
+    fn test_debug_validate_panics_after_mutation_on_broken_tree() {
+        let mut tree = RBTree::builder().debug_validate().build();
+        for (key, value) in [(10, "ten"), (5, "five"), (15, "fifteen"), (3, "three")] {
+            tree.insert(key, value);
+        }
+        let root = unsafe { tree.header.as_ref().right };
+        let mut node_3 = unsafe { root.as_ref().left.as_ref().left };
+        unsafe { node_3.as_mut().color = crate::node::Color::Black };
+
+        // the insertion itself is fine, the check after it finds the earlier corruption
+        tree.insert(20, "twenty");
+    }
+
+    #[test]
+    fn test_corrupted_sentinels_are_caught() {
+        use crate::node::Color;
+
+        let mut tree = setup_tree();
+
+        unsafe { tree.header.as_mut().color = Color::Red };
+        assert_eq!(
+            tree.validate(),
+            Err(RBTreeError::SentinelCorrupted {
+                message: "header sentinel is not black"
+            })
+        );
+        unsafe { tree.header.as_mut().color = Color::Black };
+
+        unsafe { tree.nil.as_mut().color = Color::Red };
+        assert_eq!(
+            tree.validate(),
+            Err(RBTreeError::SentinelCorrupted {
+                message: "nil sentinel is not black"
+            })
+        );
+        unsafe { tree.nil.as_mut().color = Color::Black };
+
+        let mut root = unsafe { tree.header.as_ref().right };
+        let child = unsafe { root.as_ref().left };
+        unsafe { root.as_mut().parent = child };
+        assert_eq!(
+            tree.validate(),
+            Err(RBTreeError::SentinelCorrupted {
+                message: "root's parent is not the header sentinel"
+            })
+        );
+        unsafe { root.as_mut().parent = tree.header };
+
+        assert_eq!(tree.validate(), Ok(()));
     }
 }
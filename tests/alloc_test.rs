@@ -0,0 +1,122 @@
+//! Runs with an allocator that counts allocations and can be told to fail, so it lives in its
+//! own test binary.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use rb_tree::RBTree;
+
+struct FailingAlloc;
+
+thread_local! {
+    static FAIL: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for FailingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if FAIL.with(Cell::get) {
+            return std::ptr::null_mut();
+        }
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: FailingAlloc = FailingAlloc;
+
+#[test]
+fn test_try_insert_alloc_leaves_tree_unchanged_on_failure() {
+    let mut tree = RBTree::new();
+    for i in 0..10 {
+        assert_eq!(tree.try_insert_alloc(i, i * 10), Ok(None));
+    }
+
+    FAIL.with(|fail| fail.set(true));
+    let new_key = tree.try_insert_alloc(100, 0);
+    // replacing an existing value needs no allocation
+    let existing_key = tree.try_insert_alloc(5, 55);
+    FAIL.with(|fail| fail.set(false));
+
+    assert!(new_key.is_err());
+    assert_eq!(existing_key, Ok(Some(50)));
+    assert_eq!(tree.len(), 10);
+    assert_eq!(tree.get(&100), None);
+    assert_eq!(tree.get(&5), Some(&55));
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after failed allocation: {}", e);
+    }
+
+    assert_eq!(tree.try_insert_alloc(100, 0), Ok(None));
+    assert_eq!(tree.len(), 11);
+}
+
+#[test]
+fn test_reserve_exact_then_insert_does_not_allocate() {
+    let mut tree = RBTree::new();
+    tree.insert(0, 0);
+    tree.reserve_exact(100);
+    assert_eq!(tree.capacity(), 101);
+
+    // already covered by the spare pool, so nothing more is allocated
+    tree.reserve_exact(50);
+    assert_eq!(tree.capacity(), 101);
+
+    let before = ALLOCATIONS.with(Cell::get);
+    for i in 1..=100 {
+        tree.insert(i, i);
+    }
+    assert_eq!(ALLOCATIONS.with(Cell::get), before);
+    assert_eq!(tree.len(), 101);
+    assert_eq!(tree.capacity(), 101);
+    assert!(tree.validate().is_ok());
+}
+
+#[test]
+fn test_clear_keep_capacity_then_refill_does_not_allocate() {
+    let mut tree = RBTree::new();
+    for i in 0..100 {
+        tree.insert(i, i.to_string());
+    }
+
+    tree.clear_keep_capacity();
+    assert!(tree.is_empty());
+    assert_eq!(tree.capacity(), 100);
+    assert!(tree.validate().is_ok());
+
+    // the values themselves are strings, so build them up front
+    let values: Vec<_> = (0..100).map(|i: i32| (-i).to_string()).collect();
+    let before = ALLOCATIONS.with(Cell::get);
+    for (i, value) in values.into_iter().enumerate() {
+        tree.insert(i as i32, value);
+    }
+    assert_eq!(ALLOCATIONS.with(Cell::get), before);
+    assert_eq!(tree.len(), 100);
+    assert_eq!(tree.get(&7).map(String::as_str), Some("-7"));
+    assert!(tree.validate().is_ok());
+}
+
+#[test]
+fn test_iteration_does_not_allocate() {
+    let mut tree = RBTree::new();
+    for i in 0..1_000 {
+        tree.insert(i, i);
+    }
+    let other = tree.clone();
+
+    let before = ALLOCATIONS.with(Cell::get);
+    assert_eq!(tree.iter().count(), 1_000);
+    assert_eq!(tree.keys().sum::<i32>(), tree.values().sum::<i32>());
+    assert_eq!(tree.iter_from(&500).count(), 500);
+    assert!(tree == other);
+    assert_eq!(tree.cmp(&other), std::cmp::Ordering::Equal);
+    assert_eq!(tree.content_hash(), other.content_hash());
+    assert_eq!(ALLOCATIONS.with(Cell::get), before);
+}
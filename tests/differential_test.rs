@@ -6,6 +6,7 @@ use std::collections::BTreeMap;
 enum Op<K, V> {
     Insert(K, V),
     Remove(K),
+    RemoveRange(K, K),
 }
 
 proptest! {
@@ -14,6 +15,7 @@ proptest! {
         ops in prop::collection::vec(prop_oneof![
             (any::<u16>(), any::<u16>()).prop_map(|(k, v)| Op::Insert(k, v)),
             any::<u16>().prop_map(Op::Remove),
+            (any::<u16>(), 0..1000u16).prop_map(|(lo, len)| Op::RemoveRange(lo, lo.saturating_add(len))),
         ], 1..2000)
     ) {
         let mut my_tree = RBTree::new();
@@ -29,12 +31,18 @@ proptest! {
                     my_tree.remove(&k);
                     std_tree.remove(&k);
                 }
+                Op::RemoveRange(lo, hi) => {
+                    let removed = my_tree.remove_range_fast::<&u16, _>(lo..hi);
+                    let before = std_tree.len();
+                    std_tree.retain(|k, _| !(lo..hi).contains(k));
+                    assert_eq!(removed, before - std_tree.len());
+                }
             }
 
-            if i % 100 == 0 {
-                if let Err(e) = my_tree.validate() {
-                    panic!("Tree invalid after remove iteration {}: {}", i, e);
-                }
+            if i % 100 == 0
+                && let Err(e) = my_tree.validate_dbg()
+            {
+                panic!("Tree invalid after remove iteration {}: {}", i, e);
             }
 
             assert_eq!(my_tree.len(), std_tree.len());
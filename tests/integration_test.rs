@@ -1,4 +1,7 @@
-use rb_tree::RBTree;
+#[cfg(feature = "std")]
+use rb_tree::RwRBTree;
+use rb_tree::{Color, KeyNotFound, MergeItem, RBSet, RBTree};
+use std::cmp::Ordering;
 
 #[test]
 fn test_new_tree_is_valid() {
@@ -312,10 +315,10 @@ fn test_large_tree_validation() {
         tree.insert(key, format!("large_value_{}", key));
 
         // Validate every 100 insertions to avoid too much overhead
-        if key % 100 == 0 {
-            if let Err(e) = tree.validate() {
-                panic!("Large tree invalid after inserting up to {}: {}", key, e);
-            }
+        if key % 100 == 0
+            && let Err(e) = tree.validate()
+        {
+            panic!("Large tree invalid after inserting up to {}: {}", key, e);
         }
     }
 
@@ -329,10 +332,10 @@ fn test_large_tree_validation() {
         tree.remove(&key);
 
         // Validate every 100 removals
-        if key % 100 == 0 {
-            if let Err(e) = tree.validate() {
-                panic!("Large tree invalid after removing up to {}: {}", key, e);
-            }
+        if key % 100 == 0
+            && let Err(e) = tree.validate()
+        {
+            panic!("Large tree invalid after removing up to {}: {}", key, e);
         }
     }
 
@@ -388,3 +391,2573 @@ fn test_mixed_operations_validation() {
         }
     }
 }
+
+#[test]
+fn test_successor_mut_and_predecessor_mut() {
+    let mut tree = RBTree::new();
+    for key in [10, 20, 30, 40] {
+        tree.insert(key, key * 10);
+    }
+
+    if let Some((k, mut v)) = tree.successor_mut(&20) {
+        assert_eq!(*k, 30);
+        *v += 1;
+    }
+    assert_eq!(tree.get(&30), Some(&301));
+    assert_eq!(tree.get(&20), Some(&200));
+
+    if let Some((k, mut v)) = tree.predecessor_mut(&20) {
+        assert_eq!(*k, 10);
+        *v += 1;
+    }
+    assert_eq!(tree.get(&10), Some(&101));
+    assert_eq!(tree.get(&20), Some(&200));
+
+    // absent keys resolve to their neighbors
+    assert_eq!(tree.successor_mut(&25).map(|(k, _)| *k), Some(30));
+    assert_eq!(tree.predecessor_mut(&25).map(|(k, _)| *k), Some(20));
+
+    // no neighbor past the ends
+    assert!(tree.successor_mut(&40).is_none());
+    assert!(tree.predecessor_mut(&10).is_none());
+    assert!(tree.predecessor_mut(&5).is_none());
+
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after editing neighbors: {}", e);
+    }
+}
+
+#[test]
+fn test_builder_with_capacity() {
+    use std::ops::Bound;
+
+    let mut tree = RBTree::builder()
+        .comparator(|a: &i32, b: &i32| b.cmp(a))
+        .capacity(8)
+        .build();
+    assert_eq!(tree.len(), 0);
+    assert_eq!(tree.capacity(), 8);
+
+    for key in 0..8 {
+        tree.insert(key, key * 10);
+    }
+    assert_eq!(tree.capacity(), 8);
+
+    let keys: Vec<_> = tree.iter().map(|(k, _)| *k).collect();
+    assert_eq!(keys, vec![7, 6, 5, 4, 3, 2, 1, 0]);
+    assert_eq!(tree.get(&3), Some(&30));
+    assert_eq!(tree.min_key(), Some(&7));
+    // ranges run in the tree's order, from 5 down to 2
+    let range: Vec<_> = tree
+        .range((Bound::Included(5), Bound::Included(2)))
+        .map(|(k, _)| *k)
+        .collect();
+    assert_eq!(range, vec![5, 4, 3, 2]);
+
+    // past the preallocated nodes the tree grows as usual
+    tree.insert(8, 80);
+    assert_eq!(tree.len(), 9);
+    assert_eq!(tree.capacity(), 9);
+    assert_eq!(tree.remove(&0), Some(0));
+    assert_eq!(tree.max_key(), Some(&1));
+
+    if let Err(e) = tree.validate() {
+        panic!("Tree built with capacity is invalid: {:?}", e);
+    }
+}
+
+#[test]
+fn test_builder_debug_validate() {
+    let mut tree = RBTree::builder().debug_validate().capacity(4).build();
+    for key in [5, 3, 8, 1, 4, 7, 9, 2, 6] {
+        tree.insert(key, key);
+    }
+    for key in [3, 8, 5] {
+        assert_eq!(tree.remove(&key), Some(key));
+    }
+    *tree.entry(10).or_insert(0) += 1;
+    assert_eq!(tree.pop_first(), Some((1, 1)));
+
+    let keys: Vec<_> = tree.keys().copied().collect();
+    assert_eq!(keys, vec![2, 4, 6, 7, 9, 10]);
+}
+
+#[test]
+fn test_try_retain_stops_on_break() {
+    use std::ops::ControlFlow;
+
+    let mut tree = RBTree::new();
+    for key in 0..20 {
+        tree.insert(key, key);
+    }
+
+    // remove the first odd key, then stop scanning
+    let mut removed = 0;
+    tree.try_retain(|k, _| {
+        if removed > 0 {
+            return ControlFlow::Break(());
+        }
+        if k % 2 == 1 {
+            removed += 1;
+            ControlFlow::Continue(false)
+        } else {
+            ControlFlow::Continue(true)
+        }
+    });
+
+    assert_eq!(tree.len(), 19);
+    assert!(tree.get(&1).is_none());
+    for key in (0..20).filter(|k| *k != 1) {
+        assert_eq!(tree.get(&key), Some(&key));
+    }
+
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after try_retain: {}", e);
+    }
+
+    // without a break every rejected entry is removed, and values can be edited in place
+    tree.try_retain(|k, v| {
+        *v *= 10;
+        ControlFlow::Continue(k % 2 == 0)
+    });
+    let items: Vec<_> = tree.iter().map(|(k, v)| (*k, *v)).collect();
+    let expected: Vec<_> = (0..20).step_by(2).map(|k| (k, k * 10)).collect();
+    assert_eq!(items, expected);
+
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after try_retain: {}", e);
+    }
+}
+
+#[test]
+fn test_remove_two_children_drops_exactly_once() {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    type DropLog = Rc<RefCell<HashMap<String, usize>>>;
+
+    #[derive(Debug)]
+    struct Tracked<T> {
+        inner: T,
+        label: String,
+        log: DropLog,
+    }
+
+    impl<T> Drop for Tracked<T> {
+        fn drop(&mut self) {
+            *self.log.borrow_mut().entry(self.label.clone()).or_default() += 1;
+        }
+    }
+
+    impl<T: PartialEq> PartialEq for Tracked<T> {
+        fn eq(&self, other: &Self) -> bool {
+            self.inner == other.inner
+        }
+    }
+    impl<T: Eq> Eq for Tracked<T> {}
+    impl<T: Ord> PartialOrd for Tracked<T> {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+    impl<T: Ord> Ord for Tracked<T> {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.inner.cmp(&other.inner)
+        }
+    }
+
+    let log: DropLog = Rc::default();
+    let key = |s: &str| Tracked {
+        inner: s.to_string(),
+        label: format!("key {s}"),
+        log: log.clone(),
+    };
+    let value = |s: &str| Tracked {
+        inner: s.as_bytes().to_vec(),
+        label: format!("value {s}"),
+        log: log.clone(),
+    };
+
+    // inserting a..g in order makes "b" the root, with children "a" and "d"
+    let mut tree = RBTree::new();
+    for s in ["a", "b", "c", "d", "e", "f", "g"] {
+        tree.insert(key(s), value(s));
+    }
+    assert!(log.borrow().is_empty());
+
+    let removed = tree.remove(&key("b")).expect("root should be present");
+    assert_eq!(removed.inner, b"b".to_vec());
+    // the probe key and the stored key "b" are dropped, nothing else
+    assert_eq!(log.borrow().get("key b"), Some(&2));
+    assert_eq!(log.borrow().len(), 1);
+
+    drop(removed);
+    assert_eq!(log.borrow().get("value b"), Some(&1));
+    assert_eq!(log.borrow().len(), 2);
+
+    // the predecessor's entry moved into the root's node without being dropped or duplicated
+    assert_eq!(
+        tree.get(&key("a")).map(|v| v.inner.clone()),
+        Some(b"a".to_vec())
+    );
+    log.borrow_mut().clear();
+
+    drop(tree);
+    let log = log.borrow();
+    for s in ["a", "c", "d", "e", "f", "g"] {
+        assert_eq!(log.get(&format!("key {s}")), Some(&1), "key {s}");
+        assert_eq!(log.get(&format!("value {s}")), Some(&1), "value {s}");
+    }
+    assert_eq!(log.len(), 12);
+}
+
+#[test]
+fn test_rebuild_keeps_contents_and_balance() {
+    let mut tree = RBTree::new();
+    for key in 0..100 {
+        tree.insert(key, key.to_string());
+    }
+    let before: Vec<_> = tree.iter().map(|(k, v)| (*k, v.clone())).collect();
+
+    tree.rebuild();
+    let after: Vec<_> = tree.iter().map(|(k, v)| (*k, v.clone())).collect();
+    assert_eq!(before, after);
+    assert_eq!(tree.len(), 100);
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after rebuild: {}", e);
+    }
+
+    // rebuilding again is a no-op on contents, and the tree keeps working afterwards
+    tree.rebuild();
+    for key in (0..100).step_by(3) {
+        assert_eq!(tree.remove(&key), Some(key.to_string()));
+    }
+    tree.insert(1000, "1000".to_string());
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after operations on a rebuilt tree: {}", e);
+    }
+
+    // every size from empty up rebuilds into a valid tree
+    for size in 0..40 {
+        let mut tree = RBTree::new();
+        for key in 0..size {
+            tree.insert(key, ());
+        }
+        tree.rebuild();
+        if let Err(e) = tree.validate() {
+            panic!("Tree of size {} invalid after rebuild: {}", size, e);
+        }
+        assert_eq!(tree.iter().count(), size);
+    }
+}
+
+#[test]
+fn test_any_in_range() {
+    let mut tree = RBTree::new();
+    for key in [0, 50, 100] {
+        tree.insert(key, ());
+    }
+
+    assert!(tree.any_in_range(0..1));
+    assert!(tree.any_in_range(40..60));
+    assert!(tree.any_in_range(99..));
+    assert!(tree.any_in_range(..=0));
+    assert!(tree.any_in_range::<i32, _>(..));
+
+    assert!(!tree.any_in_range(1..50));
+    assert!(!tree.any_in_range(51..100));
+    assert!(!tree.any_in_range(101..));
+    assert!(!tree.any_in_range(..0));
+    // an inverted range contains nothing, even with a key between its bounds
+    use std::ops::Bound::{Excluded, Included};
+    assert!(!tree.any_in_range((Included(60), Excluded(40))));
+
+    let empty: RBTree<i32, ()> = RBTree::new();
+    assert!(!empty.any_in_range::<i32, _>(..));
+}
+
+#[test]
+fn test_swap_values() {
+    let mut tree = RBTree::new();
+    for key in 0..10 {
+        tree.insert(key, format!("value_{}", key));
+    }
+
+    assert!(tree.swap_values(&2, &7));
+    assert_eq!(tree.get(&2), Some(&"value_7".to_string()));
+    assert_eq!(tree.get(&7), Some(&"value_2".to_string()));
+
+    // swapping a key with itself is a no-op
+    assert!(tree.swap_values(&3, &3));
+    assert_eq!(tree.get(&3), Some(&"value_3".to_string()));
+
+    assert!(!tree.swap_values(&2, &42));
+    assert!(!tree.swap_values(&42, &7));
+    assert_eq!(tree.get(&2), Some(&"value_7".to_string()));
+    assert_eq!(tree.get(&7), Some(&"value_2".to_string()));
+
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after swapping values: {}", e);
+    }
+}
+
+#[test]
+fn test_last_gap() {
+    let mut tree = RBTree::new();
+    for key in [10, 9, 7, 6] {
+        tree.insert(key, ());
+    }
+
+    let prev = |k: &i32| k - 1;
+    assert_eq!(tree.last_gap(10, prev), 8);
+    assert_eq!(tree.last_gap(8, prev), 8);
+    assert_eq!(tree.last_gap(7, prev), 5);
+    assert_eq!(tree.last_gap(20, prev), 20);
+
+    let empty: RBTree<i32, ()> = RBTree::new();
+    assert_eq!(empty.last_gap(10, prev), 10);
+}
+
+#[test]
+fn test_split_first_and_split_last() {
+    let mut tree = RBTree::new();
+    for key in [5, 3, 8, 1, 9, 2, 7] {
+        tree.insert(key, key * 10);
+    }
+
+    let mut keys = vec![];
+    let mut rest = tree;
+    while let Some(((k, v), remaining)) = rest.split_first() {
+        assert_eq!(v, k * 10);
+        assert_eq!(remaining.len(), 6 - keys.len());
+        if let Err(e) = remaining.validate() {
+            panic!("Remaining tree invalid after split_first: {}", e);
+        }
+        keys.push(k);
+        rest = remaining;
+    }
+    assert_eq!(keys, vec![1, 2, 3, 5, 7, 8, 9]);
+
+    let mut tree = RBTree::new();
+    for key in [5, 3, 8] {
+        tree.insert(key, ());
+    }
+    let ((last, ()), rest) = tree.split_last().unwrap();
+    assert_eq!(last, 8);
+    assert_eq!(rest.iter().map(|(k, _)| *k).collect::<Vec<_>>(), vec![3, 5]);
+
+    let empty: RBTree<i32, ()> = RBTree::new();
+    assert!(empty.split_last().is_none());
+}
+
+#[test]
+fn test_pop_first_and_pop_last() {
+    let mut tree = RBTree::new();
+    for key in 0..10 {
+        tree.insert(key, key);
+    }
+
+    assert_eq!(tree.pop_first(), Some((0, 0)));
+    assert_eq!(tree.pop_last(), Some((9, 9)));
+    assert_eq!(tree.len(), 8);
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after popping: {}", e);
+    }
+
+    while tree.pop_last().is_some() {}
+    assert!(tree.is_empty());
+    assert_eq!(tree.pop_first(), None);
+}
+
+#[test]
+fn test_get_mut_or_insert_with() {
+    let mut tree = RBTree::new();
+    for key in [10, 20, 30] {
+        tree.insert(key, vec![key]);
+    }
+
+    let (mut value, existed) = tree.get_mut_or_insert_with(15, Vec::new);
+    assert!(!existed);
+    assert!(value.is_empty());
+    value.push(1);
+    drop(value);
+
+    let (mut value, existed) = tree.get_mut_or_insert_with(15, || unreachable!());
+    assert!(existed);
+    value.push(2);
+    drop(value);
+
+    let (mut value, existed) = tree.get_mut_or_insert_with(20, Vec::new);
+    assert!(existed);
+    value.push(21);
+    drop(value);
+
+    assert_eq!(tree.get(&15), Some(&vec![1, 2]));
+    assert_eq!(tree.get(&20), Some(&vec![20, 21]));
+    assert_eq!(tree.len(), 4);
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after get_mut_or_insert_with: {}", e);
+    }
+}
+
+#[test]
+fn test_fold_and_sum_values() {
+    let mut tree: RBTree<i32, i32> = RBTree::new();
+    assert_eq!(tree.sum_values(), 0);
+
+    for i in 1..=100 {
+        tree.insert(i, i * 2);
+    }
+
+    assert_eq!(tree.sum_values(), (1..=100).map(|i| i * 2).sum::<i32>());
+    assert_eq!(tree.fold_values(0, |acc, v| acc.max(*v)), 200);
+
+    // values are visited in key order
+    let firsts = tree.fold_values(Vec::new(), |mut acc, v| {
+        if acc.len() < 3 {
+            acc.push(*v);
+        }
+        acc
+    });
+    assert_eq!(firsts, vec![2, 4, 6]);
+}
+
+#[test]
+fn test_clone_is_independent() {
+    let mut tree = RBTree::new();
+    for i in 0..50 {
+        tree.insert(i, i.to_string());
+    }
+
+    let mut copy = tree.clone();
+    if let Err(e) = copy.validate() {
+        panic!("Cloned tree is invalid: {}", e);
+    }
+    assert!(copy.iter().eq(tree.iter()));
+
+    copy.insert(100, "hundred".to_string());
+    copy.remove(&0);
+    assert_eq!(tree.len(), 50);
+    assert_eq!(tree.get(&0), Some(&"0".to_string()));
+    assert_eq!(tree.get(&100), None);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_rw_tree_concurrent_inserts_and_snapshot() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let tree = Arc::new(RwRBTree::new());
+    let writers: Vec<_> = (0..4)
+        .map(|t| {
+            let tree = Arc::clone(&tree);
+            thread::spawn(move || {
+                for i in 0..250 {
+                    tree.insert(t * 250 + i, i);
+                }
+            })
+        })
+        .collect();
+
+    // readers only ever see whole entries
+    let reader = {
+        let tree = Arc::clone(&tree);
+        thread::spawn(move || {
+            for _ in 0..100 {
+                let snapshot = tree.snapshot();
+                if let Err(e) = snapshot.validate() {
+                    panic!("Snapshot invalid during writes: {}", e);
+                }
+                assert_eq!(snapshot.len(), snapshot.iter().count());
+            }
+        })
+    };
+
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    reader.join().unwrap();
+
+    assert_eq!(tree.len(), 1000);
+    assert_eq!(tree.get_cloned(&999), Some(249));
+    assert_eq!(tree.get_cloned(&1000), None);
+
+    let snapshot = tree.snapshot();
+    tree.insert(1000, 0);
+    assert_eq!(snapshot.len(), 1000);
+    assert!(snapshot.iter().map(|(k, _)| *k).eq(0..1000));
+
+    let tree = RwRBTree::from(RBTree::from_iter([
+        ("a".to_string(), 1),
+        ("b".to_string(), 2),
+    ]));
+    assert_eq!(tree.remove("a"), Some(1));
+    assert_eq!(tree.remove("a"), None);
+    assert_eq!(tree.len(), 1);
+}
+
+#[test]
+fn test_min_key_and_max_key() {
+    let mut tree: RBTree<i32, ()> = RBTree::new();
+    assert_eq!(tree.min_key(), None);
+    assert_eq!(tree.max_key(), None);
+
+    for key in [40, 10, 70, 30, 90, 20] {
+        tree.insert(key, ());
+    }
+    assert_eq!(tree.min_key(), Some(&10));
+    assert_eq!(tree.max_key(), Some(&90));
+
+    tree.remove(&10);
+    tree.remove(&90);
+    assert_eq!(tree.min_key(), Some(&20));
+    assert_eq!(tree.max_key(), Some(&70));
+}
+
+#[test]
+fn test_entry_insert_or_replace() {
+    let mut tree = RBTree::new();
+    for i in 0..20 {
+        tree.insert(i * 2, i);
+    }
+
+    // created: no previous value
+    let (mut value, old) = tree.entry(7).insert_or_replace(100);
+    assert_eq!(old, None);
+    *value += 1;
+    drop(value);
+    assert_eq!(tree.get(&7), Some(&101));
+    assert_eq!(tree.len(), 21);
+
+    // replaced: the old value comes back
+    let (value, old) = tree.entry(8).insert_or_replace(200);
+    assert_eq!(old, Some(4));
+    assert_eq!(*value, 200);
+    drop(value);
+    assert_eq!(tree.len(), 21);
+
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after insert_or_replace: {}", e);
+    }
+}
+
+#[test]
+fn test_entry_or_insert_and_remove() {
+    let mut tree: RBTree<&str, i32> = RBTree::new();
+    for word in ["a", "b", "a", "c", "a", "b"] {
+        *tree.entry(word).or_insert(0) += 1;
+    }
+    assert_eq!(tree.get("a"), Some(&3));
+    assert_eq!(tree.get("b"), Some(&2));
+    assert_eq!(tree.get("c"), Some(&1));
+
+    tree.entry("c")
+        .and_modify(|v| *v *= 10)
+        .or_insert_with(|| unreachable!());
+    assert_eq!(tree.get("c"), Some(&10));
+
+    match tree.entry("b") {
+        rb_tree::Entry::Occupied(entry) => assert_eq!(entry.remove(), 2),
+        rb_tree::Entry::Vacant(_) => panic!("\"b\" should be occupied"),
+    }
+    assert_eq!(tree.get("b"), None);
+    assert_eq!(tree.len(), 2);
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after entry operations: {}", e);
+    }
+}
+
+#[test]
+fn test_take() {
+    let mut tree: RBTree<String, i32> = RBTree::new();
+    tree.insert("one".to_string(), 1);
+    tree.insert("two".to_string(), 2);
+
+    // borrowed lookup, like `remove`
+    assert_eq!(tree.take("one"), Some(1));
+    assert_eq!(tree.take("one"), None);
+    assert_eq!(tree.len(), 1);
+    assert_eq!(tree.remove_entry("two"), Some(("two".to_string(), 2)));
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn test_set_take_returns_stored_key() {
+    // keys that compare equal but are distinguishable
+    #[derive(Debug)]
+    struct Tagged(i32, &'static str);
+    impl PartialEq for Tagged {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+    impl Eq for Tagged {}
+    impl PartialOrd for Tagged {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+    impl Ord for Tagged {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    let mut set = RBSet::new();
+    assert!(set.insert(Tagged(1, "stored")));
+    assert!(!set.insert(Tagged(1, "duplicate")));
+    assert!(set.insert(Tagged(2, "other")));
+    assert!(set.contains(&Tagged(1, "probe")));
+
+    let taken = set.take(&Tagged(1, "probe")).unwrap();
+    assert_eq!(taken.1, "stored");
+    assert!(set.take(&Tagged(1, "probe")).is_none());
+    assert_eq!(set.len(), 1);
+    assert_eq!(set.iter().map(|t| t.0).collect::<Vec<_>>(), vec![2]);
+}
+
+#[test]
+fn test_cursor_insert_after_and_before() {
+    let mut tree = RBTree::new();
+
+    // append an ordered log through a cursor at the back
+    let mut cursor = tree.cursor_back_mut();
+    assert_eq!(cursor.key(), None);
+    for i in 0..100 {
+        cursor.insert_before(i * 10, i);
+    }
+    assert_eq!(tree.len(), 100);
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after appending through cursor: {}", e);
+    }
+
+    let mut cursor = tree.cursor_mut(&500);
+    cursor.insert_after(505, -1);
+    cursor.insert_before(495, -2);
+    assert_eq!(cursor.key(), Some(&500));
+    cursor.move_next();
+    assert_eq!(cursor.current().map(|(k, v)| (*k, *v)), Some((505, -1)));
+    cursor.move_prev();
+    cursor.move_prev();
+    assert_eq!(cursor.key(), Some(&495));
+
+    // at the ghost position, insert_after puts the entry at the front
+    let mut cursor = tree.cursor_front_mut();
+    cursor.move_prev();
+    cursor.insert_after(-10, 0);
+
+    assert_eq!(tree.len(), 103);
+    assert_eq!(tree.min_key(), Some(&-10));
+    assert!(tree.iter().map(|(k, _)| *k).is_sorted());
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after cursor insertions: {}", e);
+    }
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "insert_after: key must be less than the next key")]
+fn test_cursor_insert_out_of_order_panics() {
+    let mut tree = RBTree::new();
+    for i in [10, 20, 30] {
+        tree.insert(i, ());
+    }
+
+    let mut cursor = tree.cursor_mut(&10);
+    cursor.insert_after(25, ());
+}
+
+#[test]
+fn test_retain_range() {
+    let mut tree = RBTree::new();
+    for i in 0..20 {
+        tree.insert(i, i.to_string());
+    }
+
+    assert_eq!(tree.retain_range(5..=10), 14);
+    assert_eq!(tree.len(), 6);
+    assert!(tree.iter().map(|(k, _)| *k).eq(5..=10));
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after retain_range: {}", e);
+    }
+
+    // nothing outside the window left to remove
+    assert_eq!(tree.retain_range(0..100), 0);
+    // an empty window removes everything
+    assert_eq!(tree.retain_range(7..7), 6);
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn test_remove_range_fast_matches_remove_range() {
+    use std::ops::Bound::{self, Excluded, Included, Unbounded};
+
+    let ranges: [(Bound<i32>, Bound<i32>); 7] = [
+        (Included(1_000), Excluded(9_000)),
+        (Included(0), Included(9_999)),
+        (Unbounded, Excluded(5_000)),
+        (Excluded(5_000), Unbounded),
+        (Included(4_321), Included(4_321)),
+        (Included(20_000), Unbounded),
+        (Unbounded, Unbounded),
+    ];
+
+    for range in ranges {
+        let mut fast = RBTree::new();
+        let mut slow = RBTree::new();
+        // shuffled insertion order so the trees are not perfectly regular
+        for i in 0..10_000 {
+            let key = (i * 7_919) % 10_000;
+            fast.insert(key, key.to_string());
+            slow.insert(key, key.to_string());
+        }
+
+        let removed_fast = fast.remove_range_fast(range);
+        let removed_slow = slow.remove_range(range);
+
+        assert_eq!(removed_fast, removed_slow, "range {:?}", range);
+        assert_eq!(fast.len(), slow.len());
+        assert!(fast.iter().eq(slow.iter()), "range {:?}", range);
+        if let Err(e) = fast.validate() {
+            panic!("Tree invalid after remove_range_fast({:?}): {}", range, e);
+        }
+        if let Err(e) = slow.validate() {
+            panic!("Tree invalid after remove_range({:?}): {}", range, e);
+        }
+
+        // the tree keeps working normally afterwards
+        fast.insert(4_321, "again".to_string());
+        fast.remove(&0);
+        if let Err(e) = fast.validate() {
+            panic!("Tree invalid after reuse: {}", e);
+        }
+    }
+}
+
+#[test]
+fn test_depth_of() {
+    let mut tree = RBTree::new();
+    assert_eq!(tree.depth_of(&1), None);
+
+    for i in 0..127 {
+        tree.insert(i, ());
+    }
+
+    let depths: Vec<_> = (0..127).map(|i| tree.depth_of(&i).unwrap()).collect();
+    // exactly one key, the root, sits at depth 0
+    assert_eq!(depths.iter().filter(|&&d| d == 0).count(), 1);
+    // the smallest key is a leftmost leaf
+    assert!(depths[0] > 0);
+    // no path is longer than 2 * log2(n + 1)
+    assert!(depths.iter().all(|&d| d < 14));
+    assert_eq!(tree.depth_of(&127), None);
+}
+
+#[test]
+fn test_partition() {
+    let mut tree = RBTree::new();
+    for i in 0..10 {
+        tree.insert(i, i * 100);
+    }
+
+    let (evens, odds) = tree.partition(|k, _| k % 2 == 0);
+    assert!(evens.iter().map(|(k, _)| *k).eq((0..10).step_by(2)));
+    assert!(odds.iter().map(|(k, _)| *k).eq((1..10).step_by(2)));
+    assert_eq!(odds.get(&3), Some(&300));
+    for part in [&evens, &odds] {
+        if let Err(e) = part.validate() {
+            panic!("Partitioned tree invalid: {}", e);
+        }
+    }
+}
+
+#[test]
+fn test_node_iter_matches_keys() {
+    let mut tree = RBTree::new();
+    for i in (0..50).rev() {
+        tree.insert(i, i * 2);
+    }
+
+    assert!(tree.node_iter().map(|n| n.key()).eq(tree.keys()));
+    assert!(tree.node_iter().map(|n| n.value()).eq(tree.values()));
+
+    let root = tree.root().unwrap();
+    assert!(root.is_black());
+    assert!(root.parent().is_none());
+    for node in tree.node_iter() {
+        // children point back to their parent, and red nodes have black children
+        for child in [node.left(), node.right()].into_iter().flatten() {
+            assert_eq!(child.parent().unwrap().key(), node.key());
+            assert!(!(node.is_red() && child.is_red()));
+        }
+    }
+
+    let empty: RBTree<i32, i32> = RBTree::new();
+    assert!(empty.root().is_none());
+    assert_eq!(empty.node_iter().count(), 0);
+}
+
+#[test]
+fn test_range_on_large_tree() {
+    use std::collections::BTreeMap;
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    let mut tree = RBTree::new();
+    let mut reference = BTreeMap::new();
+    for i in 0..100_000u32 {
+        // every third key, in scattered order
+        let key = (i.wrapping_mul(2_654_435_761) % 100_000) * 3;
+        tree.insert(key, i);
+        reference.insert(key, i);
+    }
+
+    let mid = 150_000;
+    assert!(tree.range(mid..mid + 10).eq(reference.range(mid..mid + 10)));
+    assert!(
+        tree.range((Excluded(mid + 1), Included(mid + 30)))
+            .eq(reference.range((Excluded(mid + 1), Included(mid + 30))))
+    );
+    assert!(tree.range(..10).eq(reference.range(..10)));
+    assert!(tree.range(299_990..).eq(reference.range(299_990..)));
+    assert!(
+        tree.range((Excluded(4), Unbounded))
+            .take(5)
+            .eq(reference.range(5..).take(5))
+    );
+    assert_eq!(tree.range(mid + 1..mid + 3).next(), None);
+    assert!(
+        tree.range(1_000..2_000)
+            .rev()
+            .eq(reference.range(1_000..2_000).rev())
+    );
+}
+
+#[test]
+fn test_get_index_matches_iter_nth() {
+    let mut tree = RBTree::new();
+    for i in 0..300 {
+        tree.insert((i * 37) % 300, i);
+    }
+    for i in (0..300).step_by(3) {
+        tree.remove(&i);
+    }
+
+    for i in 0..tree.len() {
+        assert_eq!(tree.get_index(i), tree.iter().nth(i));
+    }
+    assert_eq!(tree.get_index(tree.len()), None);
+
+    let (key, mut value) = tree.get_index_mut(0).unwrap();
+    assert_eq!(*key, 1);
+    *value = -1;
+    drop(value);
+    assert_eq!(tree.get(&1), Some(&-1));
+    assert!(tree.get_index_mut(200).is_none());
+}
+
+#[test]
+fn test_remove_index() {
+    let mut tree = RBTree::new();
+    for i in (0..100).rev() {
+        tree.insert(i, i.to_string());
+    }
+
+    assert_eq!(tree.remove_index(50), Some((50, "50".to_string())));
+    assert_eq!(tree.remove_index(99), None);
+
+    let mut removed = vec![];
+    while let Some((key, _)) = tree.remove_index(0) {
+        removed.push(key);
+        if let Err(e) = tree.validate() {
+            panic!("Tree invalid after remove_index(0): {}", e);
+        }
+    }
+    assert!(removed.into_iter().eq((0..100).filter(|&k| k != 50)));
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn test_get_cloned() {
+    use std::sync::Arc;
+
+    let shared = Arc::new(vec![0u8; 1024]);
+    let mut tree = RBTree::new();
+    for i in 0..50 {
+        let value = if i % 2 == 0 {
+            Arc::clone(&shared)
+        } else {
+            Arc::new(vec![i as u8])
+        };
+        tree.insert(i, value);
+    }
+
+    for i in 0..60 {
+        assert_eq!(tree.get_cloned(&i), tree.get(&i).cloned());
+    }
+
+    let value = tree.get_cloned(&10).unwrap();
+    assert!(Arc::ptr_eq(&value, &shared));
+    assert_eq!(Arc::strong_count(&shared), 27);
+}
+
+#[test]
+fn test_content_hash() {
+    let mut a = RBTree::new();
+    let mut b = RBTree::new();
+    for i in 0..100 {
+        a.insert(i, i.to_string());
+    }
+    for i in (0..100).rev() {
+        b.insert(i, i.to_string());
+    }
+    assert_eq!(a.content_hash(), b.content_hash());
+    assert_eq!(a.content_hash(), a.clone().content_hash());
+
+    b.insert(42, "forty-two".to_string());
+    assert_ne!(a.content_hash(), b.content_hash());
+
+    b.insert(42, "42".to_string());
+    b.remove(&99);
+    assert_ne!(a.content_hash(), b.content_hash());
+}
+
+#[test]
+fn test_try_for_each_stops_early() {
+    let tree: RBTree<_, _> = (1..=10).map(|i| (i, i * 10)).collect();
+
+    let mut visited = vec![];
+    let result = tree.try_for_each(|k, v| {
+        visited.push(*k);
+        if visited.len() == 3 { Err(*v) } else { Ok(()) }
+    });
+    assert_eq!(result, Err(30));
+    assert_eq!(visited, vec![1, 2, 3]);
+
+    let mut sum = 0;
+    let result: Result<(), ()> = tree.try_for_each(|_, v| {
+        sum += v;
+        Ok(())
+    });
+    assert_eq!(result, Ok(()));
+    assert_eq!(sum, 550);
+}
+
+#[test]
+fn test_insert_with_hint_matches_insert() {
+    use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut random: Vec<u32> = (0..2_000).collect();
+    random.shuffle(&mut rng);
+    let sorted: Vec<u32> = (0..2_000).collect();
+    let descending: Vec<u32> = (0..2_000).rev().collect();
+    // nearly sorted with repeats, so hints both hit and miss
+    let nearly_sorted: Vec<u32> = (0..2_000).map(|i| i + rng.random_range(0..5)).collect();
+
+    for keys in [&random, &sorted, &descending, &nearly_sorted] {
+        let mut hinted = RBTree::new();
+        let mut plain = RBTree::new();
+        let mut hint = None;
+
+        for (i, &key) in keys.iter().enumerate() {
+            assert_eq!(
+                hinted.insert_with_hint(hint.as_ref(), key, i),
+                plain.insert(key, i)
+            );
+            hint = Some(key);
+
+            // removals reset the remembered position
+            if i % 300 == 299 {
+                assert_eq!(hinted.remove(&key), plain.remove(&key));
+            }
+        }
+
+        if let Err(e) = hinted.validate() {
+            panic!("Tree invalid after insert_with_hint: {}", e);
+        }
+        assert!(hinted.iter().eq(plain.iter()));
+    }
+
+    // a hint that isn't the last inserted key is ignored
+    let mut tree = RBTree::new();
+    tree.insert_with_hint(None, 10, "ten");
+    assert_eq!(tree.insert_with_hint(Some(&3), 5, "five"), None);
+    assert_eq!(tree.insert_with_hint(Some(&5), 5, "FIVE"), Some("five"));
+    assert_eq!(
+        tree.iter().collect::<Vec<_>>(),
+        vec![(&5, &"FIVE"), (&10, &"ten")]
+    );
+}
+
+#[test]
+fn test_into_iter_rev() {
+    use std::{cell::Cell, rc::Rc};
+
+    struct DropCounter(Rc<Cell<usize>>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    let drops = Rc::new(Cell::new(0));
+    let build = || {
+        let mut tree = RBTree::new();
+        for i in [5, 1, 9, 3, 7, 2, 8, 4, 6, 0] {
+            tree.insert(i, DropCounter(Rc::clone(&drops)));
+        }
+        tree
+    };
+
+    let keys: Vec<_> = build().into_iter_rev().map(|(k, _)| k).collect();
+    assert_eq!(keys, (0..10).rev().collect::<Vec<_>>());
+    assert_eq!(drops.get(), 10);
+
+    // stopping early drops the yielded values and frees the rest exactly once
+    drops.set(0);
+    let mut iter = build().into_iter_rev();
+    let (key, value) = iter.next().unwrap();
+    assert_eq!(key, 9);
+    drop(value);
+    assert_eq!(iter.next().map(|(k, _)| k), Some(8));
+    assert_eq!(drops.get(), 2);
+    drop(iter);
+    assert_eq!(drops.get(), 10);
+
+    let empty: RBTree<i32, i32> = RBTree::new();
+    assert_eq!(empty.into_iter_rev().next(), None);
+}
+
+#[test]
+fn test_first_n_and_last_n() {
+    let tree: RBTree<_, _> = [7, 2, 9, 4, 1, 8, 3, 10, 6, 5]
+        .into_iter()
+        .map(|k| (k, k * 100))
+        .collect();
+
+    assert_eq!(tree.first_n(3), vec![(&1, &100), (&2, &200), (&3, &300)]);
+    assert_eq!(tree.last_n(3), vec![(&10, &1000), (&9, &900), (&8, &800)]);
+    assert_eq!(tree.first_n(0), vec![]);
+    assert_eq!(tree.last_n(20).len(), 10);
+}
+
+#[test]
+fn test_get_promote_keeps_tree_valid() {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut tree = RBTree::new();
+    for i in 0..2_000u32 {
+        tree.insert(i.wrapping_mul(2_654_435_761) % 10_007, i);
+    }
+    let keys: Vec<u32> = tree.keys().copied().collect();
+
+    for _ in 0..5_000 {
+        let key = keys[rng.random_range(0..keys.len())];
+        let depth = tree.depth_of(&key).unwrap();
+        let value = tree.get_promote(&key).copied();
+        assert_eq!(value, tree.get(&key).copied());
+        assert!(tree.depth_of(&key).unwrap() <= depth);
+        if let Err(e) = tree.validate() {
+            panic!("Tree invalid after get_promote({}): {}", key, e);
+        }
+    }
+
+    assert_eq!(tree.get_promote(&10_008), None);
+    assert!(tree.keys().copied().eq(keys.iter().copied()));
+}
+
+#[test]
+fn test_augment_interval_overlaps() {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+    use rb_tree::{Augment, NodeRef, OrdComparator};
+
+    #[derive(Debug, Clone)]
+    struct Interval {
+        end: u32,
+    }
+
+    // the largest end in each subtree
+    struct MaxEnd;
+
+    impl Augment<(u32, u32), Interval> for MaxEnd {
+        type Summary = u32;
+
+        fn summarize(
+            _key: &(u32, u32),
+            value: &Interval,
+            left: Option<&u32>,
+            right: Option<&u32>,
+        ) -> u32 {
+            [left, right]
+                .into_iter()
+                .flatten()
+                .fold(value.end, |max, &end| max.max(end))
+        }
+    }
+
+    type IntervalNode<'a> = NodeRef<'a, (u32, u32), Interval, OrdComparator, MaxEnd>;
+
+    // returns the subtree's true max end, checking every stored summary against it
+    fn check_summaries(node: Option<IntervalNode<'_>>) -> u32 {
+        let Some(node) = node else { return 0 };
+        let max_end = node
+            .value()
+            .end
+            .max(check_summaries(node.left()))
+            .max(check_summaries(node.right()));
+        assert_eq!(
+            *node.summary(),
+            max_end,
+            "stale summary at {:?}",
+            node.key()
+        );
+        max_end
+    }
+
+    fn overlapping(node: Option<IntervalNode<'_>>, lo: u32, hi: u32, out: &mut Vec<(u32, u32)>) {
+        let Some(node) = node else { return };
+        if *node.summary() < lo {
+            return;
+        }
+        overlapping(node.left(), lo, hi, out);
+        let &(start, end) = node.key();
+        if start <= hi && end >= lo {
+            out.push((start, end));
+        }
+        if start <= hi {
+            overlapping(node.right(), lo, hi, out);
+        }
+    }
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut tree = RBTree::with_augment::<MaxEnd>();
+    let mut intervals = vec![];
+    for i in 0..1_000 {
+        let start = rng.random_range(0..10_000);
+        let end = start + rng.random_range(0..200);
+        // a repeated interval replaces the stored one, so track it once
+        if tree.insert((start, end), Interval { end }).is_none() {
+            intervals.push((start, end));
+        }
+
+        if i % 3 == 0 {
+            let victim = intervals.swap_remove(rng.random_range(0..intervals.len()));
+            tree.remove(&victim);
+        }
+    }
+    intervals.sort();
+    check_summaries(tree.root());
+    assert!(tree.validate().is_ok());
+
+    for _ in 0..200 {
+        let lo = rng.random_range(0..10_200);
+        let hi = lo + rng.random_range(0..100);
+        let mut found = vec![];
+        overlapping(tree.root(), lo, hi, &mut found);
+        let expected: Vec<_> = intervals
+            .iter()
+            .copied()
+            .filter(|&(start, end)| start <= hi && end >= lo)
+            .collect();
+        assert_eq!(found, expected);
+    }
+
+    // replacing a value refreshes the summaries above it
+    let &(start, end) = intervals.first().unwrap();
+    tree.insert((start, end), Interval { end: 50_000 });
+    assert_eq!(*tree.root().unwrap().summary(), 50_000);
+    check_summaries(tree.root());
+
+    // so do edits in place, once the guard handing out the value is dropped
+    tree.get_mut(&(start, end)).unwrap().end = 60_000;
+    assert_eq!(*tree.root().unwrap().summary(), 60_000);
+    check_summaries(tree.root());
+    tree.entry((start, end))
+        .and_modify(|interval| interval.end = end);
+    check_summaries(tree.root());
+    if let Some((_, mut last)) = tree.last_key_value_mut() {
+        last.end = 70_000;
+    }
+    assert_eq!(*tree.root().unwrap().summary(), 70_000);
+    tree.retain(|_, interval| {
+        interval.end = interval.end.min(5_000);
+        true
+    });
+    assert_eq!(*tree.root().unwrap().summary(), 5_000);
+    check_summaries(tree.root());
+
+    // so do clones, removals through split/join and rotations from promotion
+    let cloned = tree.clone();
+    check_summaries(cloned.root());
+    tree.remove_range_fast((2_000, 0)..(4_000, 0));
+    check_summaries(tree.root());
+    for &key in intervals.iter().take(100) {
+        tree.get_promote(&key);
+    }
+    check_summaries(tree.root());
+}
+
+#[test]
+fn test_augment_summaries_are_dropped_with_their_nodes() {
+    use rb_tree::Augment;
+    use std::rc::Rc;
+
+    thread_local! {
+        static TOKEN: Rc<()> = Rc::new(());
+    }
+    let live = || TOKEN.with(Rc::strong_count) - 1;
+
+    // every summary holds a reference to `TOKEN`
+    struct Counted;
+
+    impl Augment<i32, i32> for Counted {
+        type Summary = Rc<()>;
+
+        fn summarize(
+            _key: &i32,
+            _value: &i32,
+            _left: Option<&Rc<()>>,
+            _right: Option<&Rc<()>>,
+        ) -> Rc<()> {
+            TOKEN.with(Rc::clone)
+        }
+    }
+
+    let mut tree = RBTree::with_augment::<Counted>();
+    for i in 0..100 {
+        tree.insert(i, i);
+    }
+    for i in 0..30 {
+        tree.remove(&i);
+    }
+    tree.remove_range_fast(40..50);
+    assert_eq!(live(), tree.len());
+
+    let cloned = tree.clone();
+    assert_eq!(live(), 2 * tree.len());
+    drop(cloned);
+
+    let mut iter = tree.clone().into_iter();
+    iter.next();
+    drop(iter);
+    assert_eq!(live(), tree.len());
+
+    tree.clear_keep_capacity();
+    assert_eq!(live(), 0);
+    tree.insert(1, 1);
+    drop(tree);
+    assert_eq!(live(), 0);
+}
+
+#[test]
+fn test_clear_with() {
+    let mut tree = RBTree::new();
+    for i in [4, 8, 1, 6, 3, 9, 2, 7, 5, 0] {
+        tree.insert(i, i.to_string());
+    }
+
+    let mut delivered = vec![];
+    tree.clear_with(|k, v| delivered.push((k, v)));
+    assert_eq!(
+        delivered,
+        (0..10).map(|i| (i, i.to_string())).collect::<Vec<_>>()
+    );
+    assert!(tree.is_empty());
+    assert_eq!(tree.iter().next(), None);
+    assert!(tree.validate().is_ok());
+
+    // the tree stays usable
+    tree.insert(42, "42".to_string());
+    tree.clear();
+    assert!(tree.is_empty());
+    assert!(tree.validate().is_ok());
+}
+
+#[test]
+fn test_into_raw_from_raw_round_trip() {
+    use std::{cell::Cell, rc::Rc};
+
+    struct DropCounter(Rc<Cell<usize>>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    let drops = Rc::new(Cell::new(0));
+    // leaves spare nodes behind, which `into_raw` frees
+    let mut tree = RBTree::builder().capacity(150).build();
+    for i in 0..100 {
+        tree.insert(i, DropCounter(Rc::clone(&drops)));
+    }
+
+    let raw = tree.into_raw();
+    assert_eq!(raw.len, 100);
+    assert!(!raw.header.is_null() && !raw.nil.is_null());
+    assert_eq!(drops.get(), 0);
+
+    let mut tree = unsafe { RBTree::from_raw(raw) };
+    assert_eq!(tree.len(), 100);
+    assert!(tree.keys().copied().eq(0..100));
+
+    // the rebuilt tree is fully usable and frees every entry exactly once
+    tree.remove(&0);
+    tree.insert(100, DropCounter(Rc::clone(&drops)));
+    assert_eq!(drops.get(), 1);
+    drop(tree);
+    assert_eq!(drops.get(), 101);
+}
+
+#[test]
+fn test_from_sorted_merge() {
+    let a: Vec<_> = (0..100).step_by(3).map(|k| (k, "a")).collect();
+    let b: Vec<_> = (0..100).step_by(2).map(|k| (k, "b")).collect();
+    let c: Vec<_> = (50..150).map(|k| (k, "c")).collect();
+    let tree = RBTree::from_sorted_merge(vec![a.clone(), b.clone(), c.clone()]);
+
+    let mut expected = std::collections::BTreeMap::new();
+    for (k, v) in a.into_iter().chain(b).chain(c) {
+        expected.insert(k, v);
+    }
+    assert!(tree.iter().map(|(k, v)| (*k, *v)).eq(expected.into_iter()));
+    assert_eq!(tree.get(&6), Some(&"b"));
+    assert_eq!(tree.get(&3), Some(&"a"));
+    assert_eq!(tree.get(&60), Some(&"c"));
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after from_sorted_merge: {}", e);
+    }
+
+    // balanced: no key is deeper than a complete tree of this size allows
+    let max_depth = tree
+        .keys()
+        .map(|k| tree.depth_of(k).unwrap())
+        .max()
+        .unwrap();
+    assert_eq!(max_depth, tree.len().ilog2() as usize);
+
+    let empty: RBTree<i32, i32> = RBTree::from_sorted_merge(Vec::<Vec<(i32, i32)>>::new());
+    assert!(empty.is_empty());
+}
+
+#[test]
+#[should_panic(expected = "keys must be in ascending order")]
+fn test_from_sorted_iter_rejects_unsorted_input() {
+    RBTree::from_sorted_iter([(1, 1), (3, 3), (2, 2)]);
+}
+
+#[test]
+fn test_shape_string() {
+    let mut tree = RBTree::new();
+    assert_eq!(tree.shape_string(), ".");
+
+    for key in [10, 5, 15, 7] {
+        tree.insert(key, ());
+    }
+    assert_eq!(tree.shape_string(), "(B:10 (B:5 . (R:7 . .)) (B:15 . .))");
+
+    // 6 lands under 7, and the double rotation lifts it above 5 and 7
+    tree.insert(6, ());
+    assert_eq!(
+        tree.shape_string(),
+        "(B:10 (B:6 (R:5 . .) (R:7 . .)) (B:15 . .))"
+    );
+}
+
+#[test]
+fn test_neighbors() {
+    let tree: RBTree<_, _> = (0..50).map(|i| (i * 10, i)).collect();
+
+    // present key
+    assert_eq!(tree.neighbors(&200), (Some((&190, &19)), Some((&210, &21))));
+    // absent key between two entries
+    assert_eq!(tree.neighbors(&205), (Some((&200, &20)), Some((&210, &21))));
+    // boundary keys
+    assert_eq!(tree.neighbors(&0), (None, Some((&10, &1))));
+    assert_eq!(tree.neighbors(&490), (Some((&480, &48)), None));
+    assert_eq!(tree.neighbors(&-5), (None, Some((&0, &0))));
+    assert_eq!(tree.neighbors(&1000), (Some((&490, &49)), None));
+
+    for key in -5..500 {
+        let expected = (
+            tree.range(..key).next_back(),
+            tree.range((std::ops::Bound::Excluded(key), std::ops::Bound::Unbounded))
+                .next(),
+        );
+        assert_eq!(tree.neighbors(&key), expected);
+    }
+
+    let empty: RBTree<i32, i32> = RBTree::new();
+    assert_eq!(empty.neighbors(&1), (None, None));
+}
+
+#[test]
+fn test_truncate() {
+    let mut tree: RBTree<_, _> = [7, 2, 9, 4, 1, 8, 3, 10, 6, 5]
+        .into_iter()
+        .map(|k| (k, k.to_string()))
+        .collect();
+
+    tree.truncate(20);
+    assert_eq!(tree.len(), 10);
+
+    tree.truncate(4);
+    assert_eq!(tree.keys().copied().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    if let Err(e) = tree.validate() {
+        panic!("Tree invalid after truncate: {}", e);
+    }
+
+    tree.truncate(0);
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn test_count_leaves_and_internal() {
+    let mut tree = RBTree::new();
+    assert_eq!((tree.count_leaves(), tree.count_internal()), (0, 0));
+
+    // (B:4 (R:2 (B:1 . .) (B:3 . .)) (R:6 (B:5 . .) (B:8 (R:7 . .) (R:9 . .))))
+    for key in 1..=9 {
+        tree.insert(key, ());
+    }
+    assert_eq!(
+        tree.shape_string(),
+        "(B:4 (R:2 (B:1 . .) (B:3 . .)) (R:6 (B:5 . .) (B:8 (R:7 . .) (R:9 . .))))"
+    );
+    assert_eq!(tree.count_leaves(), 5);
+    assert_eq!(tree.count_internal(), 4);
+    assert_eq!(tree.count_leaves() + tree.count_internal(), tree.len());
+}
+
+#[test]
+fn test_get_or_insert() {
+    let mut tree = RBTree::new();
+    for key in [5, 3, 8] {
+        tree.insert(key, key * 10);
+    }
+
+    // present: the stored value is returned and the default dropped
+    assert_eq!(*tree.get_or_insert(3, 0), 30);
+    assert_eq!(tree.len(), 3);
+
+    // absent: the default is inserted
+    *tree.get_or_insert(4, 40) += 1;
+    assert_eq!(tree.get(&4), Some(&41));
+    assert_eq!(tree.len(), 4);
+    assert!(tree.validate().is_ok());
+
+    for key in 0..100 {
+        *tree.get_or_insert(key % 10, 0) += 1;
+    }
+    assert_eq!(tree.len(), 10);
+    assert_eq!(tree.get(&3), Some(&40));
+    assert_eq!(tree.get(&0), Some(&10));
+    assert!(tree.validate().is_ok());
+}
+
+#[test]
+fn test_traverse_detailed() {
+    let mut tree = RBTree::new();
+    for key in 1..=9 {
+        tree.insert(key, key * 10);
+    }
+    assert_eq!(
+        tree.shape_string(),
+        "(B:4 (R:2 (B:1 . .) (B:3 . .)) (R:6 (B:5 . .) (B:8 (R:7 . .) (R:9 . .))))"
+    );
+
+    let mut visited = Vec::new();
+    tree.traverse_detailed(|&key, &value, depth, color| visited.push((key, value, depth, color)));
+    assert_eq!(
+        visited,
+        vec![
+            (1, 10, 2, Color::Black),
+            (2, 20, 1, Color::Red),
+            (3, 30, 2, Color::Black),
+            (4, 40, 0, Color::Black),
+            (5, 50, 2, Color::Black),
+            (6, 60, 1, Color::Red),
+            (7, 70, 3, Color::Red),
+            (8, 80, 2, Color::Black),
+            (9, 90, 3, Color::Red),
+        ]
+    );
+
+    let mut calls = 0;
+    RBTree::<i32, i32>::new().traverse_detailed(|_, _, _, _| calls += 1);
+    assert_eq!(calls, 0);
+}
+
+#[test]
+fn test_insert_or_keep() {
+    let mut tree = RBTree::new();
+    assert!(tree.insert_or_keep(1, "first"));
+    assert!(tree.insert_or_keep(2, "second"));
+
+    // duplicate: the old value stays
+    assert!(!tree.insert_or_keep(1, "duplicate"));
+    assert_eq!(tree.get(&1), Some(&"first"));
+    assert_eq!(tree.len(), 2);
+
+    for key in 0..50 {
+        tree.insert_or_keep(key % 10, "later");
+    }
+    assert_eq!(tree.len(), 10);
+    assert_eq!(tree.get(&2), Some(&"second"));
+    assert_eq!(tree.get(&7), Some(&"later"));
+    assert!(tree.validate().is_ok());
+}
+
+#[test]
+fn test_peek_min_max() {
+    let mut tree = RBTree::new();
+    assert_eq!(tree.peek_min(), None);
+    assert_eq!(tree.peek_max(), None);
+
+    for key in [5, 3, 8, 1, 9] {
+        tree.insert(key, key * 10);
+    }
+    assert_eq!(tree.peek_min(), Some((&1, &10)));
+    assert_eq!(tree.peek_max(), Some((&9, &90)));
+    // peeking doesn't remove
+    assert_eq!(tree.len(), 5);
+
+    assert_eq!(tree.pop_first(), Some((1, 10)));
+    assert_eq!(tree.pop_last(), Some((9, 90)));
+    assert_eq!(tree.peek_min(), Some((&3, &30)));
+    assert_eq!(tree.peek_max(), Some((&8, &80)));
+}
+
+#[test]
+fn test_extend_sorted() {
+    let mut tree: RBTree<i32, i32> = (0..50).map(|i| (i, i)).collect();
+    tree.extend_sorted((50..100).map(|i| (i, i)));
+    assert_eq!(tree.len(), 100);
+    assert!(tree.validate().is_ok());
+    assert!(
+        tree.iter()
+            .map(|(&k, &v)| (k, v))
+            .eq((0..100).map(|i| (i, i)))
+    );
+
+    // onto an empty tree, and a short tail onto a tall tree
+    let mut empty = RBTree::new();
+    empty.extend_sorted((0..10).map(|i| (i, i)));
+    assert_eq!(empty.len(), 10);
+    assert!(empty.validate().is_ok());
+    tree.extend_sorted([(100, 100)]);
+    assert_eq!(tree.len(), 101);
+    assert!(tree.validate().is_ok());
+
+    // overlapping or unsorted input falls back to inserting
+    tree.extend_sorted([(5, -5), (200, 200)]);
+    tree.extend_sorted([(300, 300), (250, 250)]);
+    assert_eq!(tree.len(), 104);
+    assert_eq!(tree.get(&5), Some(&-5));
+    assert_eq!(tree.max_key(), Some(&300));
+    assert!(tree.validate().is_ok());
+}
+
+#[test]
+fn test_into_btree_map() {
+    use std::{cell::Cell, collections::BTreeMap, rc::Rc};
+
+    struct DropCounter(usize, Rc<Cell<usize>>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.1.set(self.1.get() + 1);
+        }
+    }
+
+    let drops = Rc::new(Cell::new(0));
+    let mut tree = RBTree::new();
+    for i in [5usize, 3, 8, 1, 9, 2] {
+        tree.insert(i, DropCounter(i * 10, Rc::clone(&drops)));
+    }
+
+    let map: BTreeMap<usize, DropCounter> = tree.into_btree_map();
+    assert_eq!(drops.get(), 0);
+    assert!(map.keys().copied().eq([1, 2, 3, 5, 8, 9]));
+    assert!(map.iter().all(|(&k, v)| v.0 == k * 10));
+
+    drop(map);
+    assert_eq!(drops.get(), 6);
+}
+
+#[test]
+fn test_from_btree_map() {
+    use std::collections::BTreeMap;
+
+    let map: BTreeMap<i32, String> = (0..100).map(|i| (i * 3, i.to_string())).collect();
+    let tree = RBTree::from(map.clone());
+
+    assert_eq!(tree.len(), map.len());
+    assert!(tree.iter().eq(map.iter()));
+    assert!(tree.validate().is_ok());
+    assert_eq!(tree.into_btree_map(), map);
+
+    assert!(RBTree::from(BTreeMap::<i32, i32>::new()).is_empty());
+}
+
+#[test]
+fn test_pop_first_while() {
+    let mut tree: RBTree<i32, i32> = (0..10).map(|i| (i, i * 10)).collect();
+
+    let due = tree.pop_first_while(|&key, _| key <= 5);
+    assert_eq!(
+        due,
+        vec![(0, 0), (1, 10), (2, 20), (3, 30), (4, 40), (5, 50)]
+    );
+    assert_eq!(tree.len(), 4);
+    assert_eq!(tree.peek_min(), Some((&6, &60)));
+    assert!(tree.validate().is_ok());
+
+    // nothing due
+    assert!(tree.pop_first_while(|&key, _| key <= 5).is_empty());
+    assert_eq!(tree.len(), 4);
+
+    assert_eq!(
+        tree.pop_last_while(|_, &value| value > 70),
+        vec![(9, 90), (8, 80)]
+    );
+    assert_eq!(tree.pop_first_while(|_, _| true), vec![(6, 60), (7, 70)]);
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn test_validate_descending_tree() {
+    let mut tree = RBTree::builder()
+        .comparator(|a: &i32, b: &i32| b.cmp(a))
+        .build();
+    for key in 0..500 {
+        tree.insert(key, key);
+    }
+    for key in (0..500).step_by(3) {
+        tree.remove(&key);
+    }
+
+    // the tree's order is the comparator's, and validation checks against that same order
+    assert!(tree.iter().map(|(_, &v)| v).is_sorted_by(|a, b| a > b));
+    if let Err(e) = tree.validate() {
+        panic!("Descending tree reported invalid: {:?}", e);
+    }
+    assert_eq!(tree.validate_verbose(), Ok(()));
+}
+
+#[test]
+fn test_debug_validate_with_descending_comparator() {
+    let mut tree = RBTree::builder()
+        .comparator(|a: &i32, b: &i32| b.cmp(a))
+        .debug_validate()
+        .build();
+    for key in 0..200 {
+        tree.insert(key, key);
+    }
+    for key in (0..200).step_by(2) {
+        tree.remove(&key);
+    }
+    assert_eq!(tree.len(), 100);
+    assert_eq!(tree.min_key(), Some(&199));
+}
+
+#[test]
+fn test_range_sum_matches_reference() {
+    use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+    use rb_tree::{OrdComparator, SumAugment};
+
+    type SumTree = RBTree<i64, i64, OrdComparator, SumAugment>;
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut keys: Vec<i64> = (0..1_000).collect();
+    keys.shuffle(&mut rng);
+
+    let mut tree = RBTree::with_range_sum();
+    for &key in &keys {
+        tree.insert(key, key * 7 % 13 - 6);
+    }
+    for &key in &keys[..300] {
+        tree.remove(&key);
+    }
+    // replacing a value refreshes the sums above it
+    for &key in &keys[300..400] {
+        tree.insert(key, 100);
+    }
+    assert!(tree.validate().is_ok());
+
+    fn reference(tree: &SumTree, lo: i64, hi: i64) -> i64 {
+        tree.iter()
+            .filter(|&(&k, _)| lo <= k && k < hi)
+            .map(|(_, v)| *v)
+            .sum()
+    }
+    let check = |tree: &SumTree, rng: &mut StdRng| {
+        for _ in 0..200 {
+            let lo = rng.random_range(-10..1_010);
+            let hi = rng.random_range(lo..1_020);
+            assert_eq!(
+                tree.range_sum(lo..hi),
+                reference(tree, lo, hi),
+                "range {lo}..{hi}"
+            );
+        }
+    };
+    check(&tree, &mut rng);
+
+    // so do edits in place through the value guards
+    for &key in &keys[400..500] {
+        *tree.get_mut(&key).unwrap() += 1_000;
+    }
+    check(&tree, &mut rng);
+    for &key in &keys[500..600] {
+        tree.entry(key).and_modify(|value| *value -= 500);
+        *tree.entry(key).or_insert(0) *= 2;
+    }
+    check(&tree, &mut rng);
+    for index in (0..tree.len()).step_by(7) {
+        *tree.get_index_mut(index).unwrap().1 = -3;
+    }
+    check(&tree, &mut rng);
+    {
+        let mut cursor = tree.cursor_front_mut();
+        loop {
+            let Some((_, mut value)) = cursor.current() else {
+                break;
+            };
+            *value += 11;
+            drop(value);
+            cursor.move_next();
+        }
+    }
+    check(&tree, &mut rng);
+
+    // and whole-tree edits, `iter_mut` being limited to trees without an `Augment`
+    tree.map_values_in_place(|key, value| *value += key % 5);
+    check(&tree, &mut rng);
+    tree.retain(|key, value| {
+        *value -= 2;
+        key % 9 != 0
+    });
+    check(&tree, &mut rng);
+    assert!(tree.validate().is_ok());
+
+    let reference = |lo, hi| reference(&tree, lo, hi);
+
+    assert_eq!(tree.range_sum(..), reference(i64::MIN, i64::MAX));
+    assert_eq!(tree.range_sum(500..), reference(500, i64::MAX));
+    assert_eq!(tree.range_sum(..=500), reference(i64::MIN, 501));
+    assert_eq!(tree.range_sum(400..400), 0);
+    let empty: RBTree<i64, i64, _, _> = RBTree::with_range_sum();
+    assert_eq!(empty.range_sum(..), 0);
+}
+
+#[test]
+fn test_clone_shares_rc_values() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let mut tree = RBTree::new();
+    for key in 0..10 {
+        tree.insert(key, Rc::new(RefCell::new(key)));
+    }
+
+    let shallow = tree.clone();
+    *tree.get(&3).unwrap().borrow_mut() = 30;
+    // `Clone` clones the `Rc`, so both trees point at the same cell
+    assert_eq!(*shallow.get(&3).unwrap().borrow(), 30);
+    assert_eq!(Rc::strong_count(tree.get(&3).unwrap()), 2);
+
+    let deep: RBTree<i32, Rc<RefCell<i32>>> = tree
+        .iter()
+        .map(|(&k, v)| (k, Rc::new(RefCell::new(*v.borrow()))))
+        .collect();
+    *tree.get(&3).unwrap().borrow_mut() = 300;
+    assert_eq!(*deep.get(&3).unwrap().borrow(), 30);
+    assert_eq!(*shallow.get(&3).unwrap().borrow(), 300);
+}
+
+#[test]
+fn test_retain_returns_removed_count() {
+    let mut tree: RBTree<i32, i32> = (0..20).map(|i| (i, i)).collect();
+
+    assert_eq!(tree.retain(|_, _| true), 0);
+    assert_eq!(tree.len(), 20);
+
+    assert_eq!(tree.retain(|&k, _| k % 4 != 0), 5);
+    assert_eq!(tree.len(), 15);
+
+    let len = tree.len();
+    assert_eq!(tree.retain(|_, _| false), len);
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn test_merge_join() {
+    let left: RBTree<i32, char> = [(1, 'a'), (3, 'b'), (5, 'c'), (7, 'd')]
+        .into_iter()
+        .collect();
+    let right: RBTree<i32, char> = [(2, 'w'), (3, 'x'), (7, 'y'), (9, 'z')]
+        .into_iter()
+        .collect();
+
+    let merged: Vec<_> = left.merge_join(&right).collect();
+    assert_eq!(
+        merged,
+        vec![
+            MergeItem::Left(&1, &'a'),
+            MergeItem::Right(&2, &'w'),
+            MergeItem::Both(&3, &'b', &'x'),
+            MergeItem::Left(&5, &'c'),
+            MergeItem::Both(&7, &'d', &'y'),
+            MergeItem::Right(&9, &'z'),
+        ]
+    );
+
+    let empty = RBTree::new();
+    assert_eq!(left.merge_join(&empty).count(), 4);
+    assert!(
+        empty
+            .merge_join(&right)
+            .all(|item| matches!(item, MergeItem::Right(..)))
+    );
+}
+
+#[test]
+fn test_insert_many_reports_displaced() {
+    let mut tree: RBTree<i32, &str> = [(1, "a"), (3, "c"), (5, "e")].into_iter().collect();
+
+    let displaced = tree.insert_many([(2, "b"), (3, "C"), (5, "E"), (6, "f"), (3, "CC")]);
+    assert_eq!(displaced, vec![(3, "c"), (5, "e"), (3, "C")]);
+    assert_eq!(
+        tree.iter().map(|(&k, &v)| (k, v)).collect::<Vec<_>>(),
+        vec![(1, "a"), (2, "b"), (3, "CC"), (5, "E"), (6, "f")]
+    );
+    assert!(tree.validate().is_ok());
+
+    assert!(tree.insert_many([(10, "j"), (11, "k")]).is_empty());
+    assert_eq!(tree.len(), 7);
+}
+
+#[test]
+fn test_validate_rb_only_agrees_with_validate() {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut tree = RBTree::new();
+    assert_eq!(tree.validate_rb_only(), Ok(()));
+
+    for _ in 0..2_000 {
+        let key = rng.random_range(0..500);
+        if rng.random_bool(0.6) {
+            tree.insert(key, key);
+        } else {
+            tree.remove(&key);
+        }
+        assert_eq!(tree.validate_rb_only(), Ok(()));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+}
+
+#[test]
+fn test_first_last_key_value_mut() {
+    let mut tree = RBTree::new();
+    assert!(tree.first_key_value_mut().is_none());
+    assert!(tree.last_key_value_mut().is_none());
+
+    for key in [5, 3, 8, 1, 9] {
+        tree.insert(key, key * 10);
+    }
+
+    let (key, mut value) = tree.first_key_value_mut().unwrap();
+    assert_eq!(*key, 1);
+    *value += 1;
+    drop(value);
+    let (key, mut value) = tree.last_key_value_mut().unwrap();
+    assert_eq!(*key, 9);
+    *value = 0;
+    drop(value);
+
+    assert_eq!(
+        tree.iter().map(|(&k, &v)| (k, v)).collect::<Vec<_>>(),
+        vec![(1, 11), (3, 30), (5, 50), (8, 80), (9, 0)]
+    );
+    assert!(tree.validate().is_ok());
+}
+
+#[test]
+fn test_dedup_adjacent_values() {
+    let mut tree: RBTree<i32, char> = [(1, 'a'), (2, 'a'), (3, 'b'), (4, 'b'), (5, 'a')]
+        .into_iter()
+        .collect();
+
+    assert_eq!(tree.dedup_adjacent_values(), 2);
+    assert_eq!(
+        tree.iter().map(|(&k, &v)| (k, v)).collect::<Vec<_>>(),
+        vec![(1, 'a'), (3, 'b'), (5, 'a')]
+    );
+    assert!(tree.validate().is_ok());
+
+    // already compressed
+    assert_eq!(tree.dedup_adjacent_values(), 0);
+
+    let mut runs: RBTree<i32, i32> = (0..1_000).map(|i| (i, i / 10)).collect();
+    assert_eq!(runs.dedup_adjacent_values(), 900);
+    assert!(runs.keys().copied().eq((0..1_000).step_by(10)));
+    assert!(runs.validate().is_ok());
+
+    assert_eq!(RBTree::<i32, i32>::new().dedup_adjacent_values(), 0);
+}
+
+#[test]
+fn test_search_path() {
+    let mut tree = RBTree::new();
+    for key in 1..=9 {
+        tree.insert(key, ());
+    }
+    assert_eq!(
+        tree.shape_string(),
+        "(B:4 (R:2 (B:1 . .) (B:3 . .)) (R:6 (B:5 . .) (B:8 (R:7 . .) (R:9 . .))))"
+    );
+
+    assert_eq!(tree.search_path(&4), vec![4]);
+    assert_eq!(tree.search_path(&7), vec![4, 6, 8, 7]);
+    // absent keys end at the node where the search falls off
+    assert_eq!(tree.search_path(&10), vec![4, 6, 8, 9]);
+    assert_eq!(tree.search_path(&0), vec![4, 2, 1]);
+
+    let strings: RBTree<String, ()> = ["b", "a", "c"]
+        .into_iter()
+        .map(|s| (s.to_string(), ()))
+        .collect();
+    assert_eq!(strings.search_path("c"), vec!["b", "c"]);
+    assert!(RBTree::<i32, ()>::new().search_path(&1).is_empty());
+}
+
+#[test]
+fn test_rotation_count() {
+    use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+    // 2, 4 and 6 each extend a red right child with no red uncle, so each takes a rotation;
+    // 3 and 5 only recolor
+    let mut tree = RBTree::new();
+    for key in 0..=6 {
+        tree.insert(key, ());
+    }
+    assert_eq!(tree.rotation_count(), 3);
+    tree.reset_rotation_count();
+    assert_eq!(tree.rotation_count(), 0);
+
+    // lookups never rotate
+    assert_eq!(tree.get(&3), Some(&()));
+    assert_eq!(tree.rotation_count(), 0);
+
+    let mut sorted = RBTree::new();
+    for key in 0..10_000 {
+        sorted.insert(key, ());
+    }
+    let mut keys: Vec<i32> = (0..10_000).collect();
+    keys.shuffle(&mut StdRng::seed_from_u64(42));
+    let mut random = RBTree::new();
+    for key in keys {
+        random.insert(key, ());
+    }
+    // sorted input rotates about once per insert, random input markedly less
+    assert!(sorted.rotation_count() > 9_000);
+    assert!(random.rotation_count() < sorted.rotation_count() * 3 / 4);
+}
+
+#[test]
+fn test_try_get_with_question_mark() {
+    use std::error::Error;
+
+    fn total(tree: &RBTree<&str, i32>, keys: &[&str]) -> Result<i32, Box<dyn Error>> {
+        let mut sum = 0;
+        for key in keys {
+            sum += tree.try_get(*key)?;
+        }
+        Ok(sum)
+    }
+
+    let tree: RBTree<&str, i32> = [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+    assert_eq!(tree.try_get("b"), Ok(&2));
+    assert_eq!(tree.try_get("z"), Err(KeyNotFound));
+
+    assert_eq!(total(&tree, &["a", "c"]).unwrap(), 4);
+    let error = total(&tree, &["a", "z"]).unwrap_err();
+    assert!(error.is::<KeyNotFound>());
+    assert_eq!(error.to_string(), "key not found");
+}
+
+#[test]
+fn test_split_off_after() {
+    let mut tree: RBTree<i32, i32> = (1..=10).map(|i| (i, i * 10)).collect();
+    let right = tree.split_off_after(&5);
+
+    assert!(tree.keys().copied().eq(1..=5));
+    assert!(
+        right
+            .iter()
+            .map(|(&k, &v)| (k, v))
+            .eq((6..=10).map(|i| (i, i * 10)))
+    );
+    assert_eq!(tree.len(), 5);
+    assert_eq!(right.len(), 5);
+    assert!(tree.validate().is_ok());
+    assert!(right.validate().is_ok());
+
+    // absent boundary keys, and splits that leave one side empty
+    let mut big: RBTree<i32, ()> = (0..1_000).map(|i| (i * 2, ())).collect();
+    let upper = big.split_off_after(&999);
+    assert_eq!(big.max_key(), Some(&998));
+    assert_eq!(upper.min_key(), Some(&1_000));
+    assert_eq!(big.len() + upper.len(), 1_000);
+    assert!(big.validate().is_ok());
+    assert!(upper.validate().is_ok());
+
+    assert!(big.split_off_after(&5_000).is_empty());
+    let mut all = big.split_off_after(&-1);
+    assert!(big.is_empty());
+    assert_eq!(all.len(), 500);
+    assert!(all.validate().is_ok());
+
+    // both halves stay usable
+    big.insert(1, ());
+    all.insert(3, ());
+    assert!(big.validate().is_ok());
+    assert!(all.validate().is_ok());
+}
+
+#[test]
+fn test_singleton() {
+    let mut single = RBTree::singleton(1, "a");
+    let mut inserted = RBTree::new();
+    inserted.insert(1, "a");
+
+    assert_eq!(single.len(), 1);
+    assert!(single.iter().eq(inserted.iter()));
+    assert_eq!(single.shape_string(), inserted.shape_string());
+    assert_eq!(single.shape_string(), "(B:1 . .)");
+    assert!(single.validate().is_ok());
+
+    // grows like any other tree
+    single.insert(2, "b");
+    single.insert(0, "z");
+    assert_eq!(single.len(), 3);
+    assert!(single.validate().is_ok());
+}
+
+#[test]
+fn test_count_between() {
+    let tree: RBTree<i32, ()> = (0..20).map(|i| (i, ())).collect();
+    assert_eq!(tree.count_between(&5, &15), 9);
+    assert_eq!(tree.count_between(&-10, &100), 20);
+    assert_eq!(tree.count_between(&5, &6), 0);
+    // an empty or inverted interval
+    assert_eq!(tree.count_between(&5, &5), 0);
+    assert_eq!(tree.count_between(&15, &5), 0);
+
+    let sparse: RBTree<i32, ()> = (0..100).map(|i| (i * 3, ())).collect();
+    for (low, high) in [(0, 299), (1, 2), (10, 50), (-5, 4), (150, 400)] {
+        let expected = sparse.keys().filter(|&&k| low < k && k < high).count();
+        assert_eq!(sparse.count_between(&low, &high), expected);
+    }
+}
+
+#[test]
+fn test_entry_or_default() {
+    let mut tree: RBTree<char, Vec<i32>> = RBTree::new();
+    for (n, c) in "abcab".chars().enumerate() {
+        tree.entry(c).or_default().push(n as i32);
+    }
+
+    assert_eq!(tree.get(&'a'), Some(&vec![0, 3]));
+    assert_eq!(tree.get(&'b'), Some(&vec![1, 4]));
+    assert_eq!(tree.get(&'c'), Some(&vec![2]));
+    assert_eq!(tree.len(), 3);
+    assert!(tree.validate().is_ok());
+}
+
+#[test]
+fn test_into_reversed() {
+    use std::cmp::Reverse;
+
+    let tree: RBTree<i32, String> = (0..100).map(|i| (i, i.to_string())).collect();
+    let mut reversed = tree.into_reversed();
+
+    assert_eq!(reversed.len(), 100);
+    assert!(reversed.iter().map(|(Reverse(k), _)| *k).eq((0..100).rev()));
+    assert_eq!(reversed.get(&Reverse(42)), Some(&"42".to_string()));
+    assert!(reversed.validate().is_ok());
+
+    reversed.insert(Reverse(1_000), "1000".to_string());
+    assert_eq!(reversed.min_key(), Some(&Reverse(1_000)));
+    assert!(reversed.validate().is_ok());
+}
+
+#[test]
+fn test_entry_or_insert_entry() {
+    let mut tree: RBTree<i32, &str> = [(1, "one"), (3, "three")].into_iter().collect();
+
+    // vacant: inserted, then removed again through the returned handle
+    let entry = tree.entry(2).or_insert_entry("two");
+    assert_eq!(entry.key(), &2);
+    assert_eq!(entry.get(), &"two");
+    assert_eq!(entry.remove(), "two");
+    assert_eq!(tree.get(&2), None);
+    assert_eq!(tree.len(), 2);
+
+    // occupied: the stored value is kept
+    let mut entry = tree.entry(3).or_insert_entry("unused");
+    assert_eq!(entry.insert("THREE"), "three");
+    assert_eq!(tree.get(&3), Some(&"THREE"));
+    assert!(tree.validate().is_ok());
+}
+
+#[test]
+fn test_from_unsorted_matches_inserts() {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    let mut rng = StdRng::seed_from_u64(42);
+    // plenty of repeated keys, so the last-value-wins rule is exercised
+    let pairs: Vec<(u32, usize)> = (0..5_000)
+        .map(|i| (rng.random_range(0..2_000), i))
+        .collect();
+
+    let mut inserted = RBTree::new();
+    for &(key, value) in &pairs {
+        inserted.insert(key, value);
+    }
+    let built = RBTree::from_unsorted(pairs);
+
+    assert_eq!(built.len(), inserted.len());
+    assert!(built.iter().eq(inserted.iter()));
+    assert!(built.validate().is_ok());
+
+    assert!(RBTree::<u32, ()>::from_unsorted(vec![]).is_empty());
+}
+
+#[test]
+fn test_for_each_mut_with_depth() {
+    let mut tree = RBTree::new();
+    for key in 1..=9 {
+        tree.insert(key, usize::MAX);
+    }
+    assert_eq!(
+        tree.shape_string(),
+        "(B:4 (R:2 (B:1 . .) (B:3 . .)) (R:6 (B:5 . .) (B:8 (R:7 . .) (R:9 . .))))"
+    );
+
+    let mut visited = vec![];
+    tree.for_each_mut_with_depth(|&key, value, depth| {
+        visited.push(key);
+        *value = depth;
+    });
+    assert_eq!(visited, (1..=9).collect::<Vec<_>>());
+
+    let depths: Vec<_> = tree.values().copied().collect();
+    assert_eq!(depths, vec![2, 1, 2, 0, 2, 1, 3, 2, 3]);
+
+    // subtree sums follow the new values
+    let mut sums = RBTree::with_range_sum();
+    for key in 1..=9 {
+        sums.insert(key, 0);
+    }
+    sums.for_each_mut_with_depth(|_, value, depth| *value = depth);
+    assert_eq!(sums.range_sum(..), 16);
+    assert_eq!(sums.range_sum(6..), 9);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_render_tree_aligns_long_keys() {
+    let mut tree = RBTree::new();
+    for i in 0..5 {
+        tree.insert(
+            format!("a-rather-long-key-number-{i}-{}", "x".repeat(40)),
+            i,
+        );
+    }
+
+    let rendered = tree.render_tree_with_width(20);
+    let width = |line: &str| -> usize {
+        line.chars()
+            .map(|c| if matches!(c, '🔴' | '⚫') { 2 } else { 1 })
+            .sum()
+    };
+    let box_lines: Vec<_> = rendered
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .collect();
+    assert_eq!(box_lines.len(), 7);
+    let box_width = width(box_lines[0]);
+    assert!(box_lines.iter().all(|line| width(line) == box_width));
+
+    let widest_entry = rendered
+        .lines()
+        .skip(box_lines.len())
+        .map(width)
+        .max()
+        .unwrap();
+    assert_eq!(box_width, widest_entry + 2);
+
+    let small: RBTree<_, _> = (1..=3).map(|i| (i, i)).collect();
+    let small_box = small.render_tree();
+    assert!(small_box.lines().take(7).all(|line| width(line) == 64));
+
+    let empty = RBTree::<String, i32>::new().render_tree();
+    let empty_lines: Vec<_> = empty.lines().collect();
+    assert!(empty_lines.iter().all(|line| width(line) == 64));
+}
+
+#[test]
+fn test_range_mut_pair() {
+    let mut tree: RBTree<i32, i32> = (0..10).map(|key| (key, key)).collect();
+
+    // double buffering: each window reads from the other while writing its own values
+    let (front, back) = tree.range_mut_pair(0..5, 5..10).unwrap();
+    for ((_, a), (_, b)) in front.zip(back.rev()) {
+        core::mem::swap(a, b);
+        *a *= 10;
+    }
+    let values: Vec<_> = tree.values().copied().collect();
+    assert_eq!(values, vec![90, 80, 70, 60, 50, 4, 3, 2, 1, 0]);
+    assert!(tree.validate().is_ok());
+
+    assert!(tree.range_mut_pair(0..5, 4..10).is_none());
+    assert!(tree.range_mut_pair(..=3, 3..).is_none());
+
+    // bounds that overlap between keys share no entry
+    let mut sparse: RBTree<i32, i32> = [(0, 0), (10, 0)].into_iter().collect();
+    let (low, high) = sparse.range_mut_pair(..6, 4..).unwrap();
+    assert_eq!(low.count() + high.count(), 2);
+
+    // an empty range pairs with anything
+    let (empty, all) = tree.range_mut_pair(20.., ..).unwrap();
+    assert_eq!(empty.count(), 0);
+    assert_eq!(all.count(), 10);
+}
+
+#[test]
+fn test_map_values_in_place() {
+    let mut tree: RBTree<u32, u32> = (0..100).map(|key| (key, key)).collect();
+    let shape = tree.shape_string();
+
+    let mut seen = vec![];
+    tree.map_values_in_place(|&key, value| {
+        seen.push(key);
+        *value *= 2;
+    });
+    assert_eq!(seen, (0..100).collect::<Vec<_>>());
+    assert!(tree.iter().all(|(key, value)| *value == key * 2));
+    assert_eq!(tree.shape_string(), shape);
+
+    // subtree sums follow the new values
+    let mut sums = RBTree::with_range_sum();
+    for key in 0..100u32 {
+        sums.insert(key, key);
+    }
+    sums.map_values_in_place(|_, value| *value *= 2);
+    assert_eq!(
+        sums.range_sum(10..20),
+        (10..20).map(|key| key * 2).sum::<u32>()
+    );
+    assert_eq!(sums.range_sum(..), 9900);
+}
+
+#[test]
+fn test_map_values() {
+    let mut tree = RBTree::new();
+    for key in [50, 20, 80, 10, 30, 70, 90, 60] {
+        tree.insert(key, -key);
+    }
+
+    let strings: RBTree<i32, String> = tree.map_values(|value| value.to_string());
+    assert!(strings.validate().is_ok());
+    assert_eq!(strings.len(), tree.len());
+    assert!(strings.keys().eq(tree.keys()));
+    assert_eq!(strings.get(&60).map(String::as_str), Some("-60"));
+
+    // same shape and colors, so nothing was rebalanced
+    assert_eq!(strings.shape_string(), tree.shape_string());
+}
+
+#[test]
+fn test_filter_map_values() {
+    let tree: RBTree<u32, u32> = (0..100).map(|key| (key, key * key)).collect();
+
+    let evens = tree.filter_map_values(|key, value| (key % 2 == 0).then(|| format!("{value}")));
+    assert!(evens.validate().is_ok());
+    assert_eq!(evens.len(), 50);
+    assert!(evens.keys().copied().eq((0..100).step_by(2)));
+    assert_eq!(evens.get(&8).map(String::as_str), Some("64"));
+    assert_eq!(evens.get(&9), None);
+
+    let none = tree.filter_map_values(|_, _| None::<()>);
+    assert!(none.is_empty());
+    assert!(none.validate().is_ok());
+}
+
+#[test]
+fn test_pairs() {
+    let tree: RBTree<i32, char> = [(3, 'c'), (1, 'a'), (2, 'b')].into_iter().collect();
+    let pairs: Vec<_> = tree.pairs().collect();
+    assert_eq!(
+        pairs,
+        vec![((&1, &'a'), (&2, &'b')), ((&2, &'b'), (&3, &'c'))]
+    );
+
+    let deltas: Vec<_> = tree.pairs().map(|((a, _), (b, _))| b - a).collect();
+    assert_eq!(deltas, vec![1, 1]);
+
+    assert_eq!(RBTree::singleton(1, 'a').pairs().count(), 0);
+    assert_eq!(RBTree::<i32, char>::new().pairs().count(), 0);
+}
+
+#[test]
+fn test_group_by() {
+    let tree: RBTree<u32, u32> = (0..10).map(|key| (key, key * 10)).collect();
+
+    let mut calls = 0;
+    let buckets: Vec<Vec<u32>> = tree
+        .group_by(|key| {
+            calls += 1;
+            key / 3
+        })
+        .map(|run| run.into_iter().map(|(key, _)| *key).collect())
+        .collect();
+    assert_eq!(
+        buckets,
+        vec![vec![0, 1, 2], vec![3, 4, 5], vec![6, 7, 8], vec![9]]
+    );
+    assert_eq!(calls, 10);
+
+    let sums: Vec<u32> = tree
+        .group_by(|key| key % 2 == 0)
+        .map(|run| run.iter().map(|(_, value)| **value).sum())
+        .collect();
+    assert_eq!(sums.len(), 10);
+
+    assert_eq!(RBTree::<u32, u32>::new().group_by(|key| *key).count(), 0);
+}
+
+#[test]
+fn test_closest_by() {
+    let tree: RBTree<u32, &str> = [(10, "ten"), (20, "twenty"), (30, "thirty")]
+        .into_iter()
+        .collect();
+    let closest = |probe: u32| {
+        tree.closest_by(&probe, |a, b| probe.abs_diff(*a).cmp(&probe.abs_diff(*b)))
+            .map(|(key, _)| *key)
+    };
+
+    assert_eq!(closest(16), Some(20));
+    assert_eq!(closest(13), Some(10));
+    assert_eq!(closest(20), Some(20));
+    // ties go to the smaller key
+    assert_eq!(closest(25), Some(20));
+    assert_eq!(closest(0), Some(10));
+    assert_eq!(closest(100), Some(30));
+
+    assert_eq!(
+        RBTree::<u32, ()>::new().closest_by(&5, |_, _| Ordering::Equal),
+        None
+    );
+}
+
+#[test]
+fn test_retain_indexed() {
+    let mut tree: RBTree<u32, u32> = (0..10).map(|key| (key * 5, key)).collect();
+
+    let removed = tree.retain_indexed(|index, _, _| index % 2 == 0);
+    assert_eq!(removed, 5);
+    assert!(tree.keys().copied().eq([0, 10, 20, 30, 40]));
+    assert!(tree.validate().is_ok());
+
+    // positions are those before the call, not shifted by earlier removals
+    let mut seen = vec![];
+    tree.retain_indexed(|index, &key, value| {
+        seen.push(index);
+        *value = index as u32;
+        key != 0
+    });
+    assert_eq!(seen, vec![0, 1, 2, 3, 4]);
+    let values: Vec<_> = tree.values().copied().collect();
+    assert_eq!(values, vec![1, 2, 3, 4]);
+}
+
+#[test]
+fn test_drain_sorted() {
+    use std::{cell::Cell, rc::Rc};
+
+    struct DropCounter(u32, Rc<Cell<usize>>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.1.set(self.1.get() + 1);
+        }
+    }
+
+    let drops = Rc::new(Cell::new(0));
+    let mut tree = RBTree::new();
+    for key in [5, 1, 9, 3, 7, 2, 8, 4, 6, 0] {
+        tree.insert(key, DropCounter(key * 10, Rc::clone(&drops)));
+    }
+
+    let entries = tree.drain_sorted();
+    assert_eq!(drops.get(), 0);
+    let keys: Vec<_> = entries.iter().map(|(key, _)| *key).collect();
+    assert_eq!(keys, (0..10).collect::<Vec<_>>());
+    assert!(entries.iter().all(|(key, value)| value.0 == key * 10));
+
+    drop(entries);
+    assert_eq!(drops.get(), 10);
+    assert!(RBTree::<u32, u32>::new().drain_sorted().is_empty());
+
+    // spare nodes are freed with the emptied tree
+    let mut tree: RBTree<_, _> = (0..1_000).rev().map(|key| (key, key * 2)).collect();
+    tree.reserve_exact(10);
+    let entries = tree.drain_sorted();
+    assert!(entries.into_iter().eq((0..1_000).map(|key| (key, key * 2))));
+}
+
+#[test]
+fn test_is_balanced() {
+    // neither Clone nor Debug
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    struct Opaque(u32);
+
+    let mut tree = RBTree::new();
+    assert!(tree.is_balanced());
+    for key in 0..500 {
+        tree.insert(Opaque((key * 7919) % 500), ());
+        assert!(tree.is_balanced());
+    }
+    for key in (0..500).step_by(3) {
+        tree.remove(&Opaque(key));
+    }
+    assert!(tree.is_balanced());
+
+    let built = RBTree::from_sorted_iter((0..100).map(|key| (key, key)));
+    assert!(built.is_balanced());
+}
+
+#[test]
+fn test_extend_absent() {
+    let mut settings = RBTree::new();
+    settings.insert("theme", "dark");
+    settings.insert("font_size", "14");
+
+    let defaults = [
+        ("theme", "light"),
+        ("font_size", "12"),
+        ("tab_width", "4"),
+        ("wrap", "off"),
+    ];
+    assert_eq!(settings.extend_absent(defaults), 2);
+
+    assert_eq!(settings.len(), 4);
+    assert_eq!(settings.get(&"theme"), Some(&"dark"));
+    assert_eq!(settings.get(&"font_size"), Some(&"14"));
+    assert_eq!(settings.get(&"tab_width"), Some(&"4"));
+    assert_eq!(settings.get(&"wrap"), Some(&"off"));
+
+    // a repeated key within the input keeps its first value
+    assert_eq!(settings.extend_absent([("lang", "en"), ("lang", "fr")]), 1);
+    assert_eq!(settings.get(&"lang"), Some(&"en"));
+    assert!(settings.validate().is_ok());
+}
+
+#[test]
+fn test_tree_ordering() {
+    let tree = |pairs: &[(u32, u32)]| pairs.iter().copied().collect::<RBTree<_, _>>();
+    let base = tree(&[(1, 10), (2, 20), (3, 30)]);
+
+    // the first diverging key decides, regardless of what follows
+    assert!(base < tree(&[(1, 10), (4, 0)]));
+    assert!(base > tree(&[(0, 99), (9, 99)]));
+    // then the value of the first diverging entry
+    assert!(base < tree(&[(1, 10), (2, 21), (3, 0)]));
+    assert_eq!(
+        base.cmp(&tree(&[(1, 10), (2, 19), (3, 99)])),
+        Ordering::Greater
+    );
+    // a prefix sorts first
+    assert!(tree(&[(1, 10)]) < base);
+    assert!(RBTree::new() < base);
+
+    // equal entries compare equal whatever the insertion order
+    let reversed = tree(&[(3, 30), (2, 20), (1, 10)]);
+    assert_eq!(base.cmp(&reversed), Ordering::Equal);
+    assert_eq!(base, reversed);
+    assert_ne!(base, tree(&[(1, 10), (2, 20)]));
+
+    // matches BTreeMap
+    let maps: Vec<std::collections::BTreeMap<u32, u32>> = vec![
+        [(1, 1), (5, 0)].into(),
+        [(1, 1)].into(),
+        [(0, 7)].into(),
+        [(1, 0), (2, 0)].into(),
+    ];
+    let mut sorted_maps = maps.clone();
+    sorted_maps.sort();
+    let mut trees: Vec<RBTree<u32, u32>> = maps.into_iter().map(RBTree::from).collect();
+    trees.sort();
+    let trees: Vec<_> = trees.into_iter().map(RBTree::into_btree_map).collect();
+    assert_eq!(trees, sorted_maps);
+}
+
+#[test]
+fn test_get_pair_mut() {
+    let mut tree: RBTree<u32, u32> = (1..=5).map(|key| (key, key * 10)).collect();
+
+    // move part of one entry's value into its successor
+    let (a, b) = tree.get_pair_mut(&2, &3).unwrap();
+    *a -= 5;
+    *b += 5;
+    assert_eq!(tree.get(&2), Some(&15));
+    assert_eq!(tree.get(&3), Some(&35));
+
+    // argument order decides the tuple order, not key order
+    let (high, low) = tree.get_pair_mut(&5, &1).unwrap();
+    std::mem::swap(high, low);
+    assert_eq!(tree.get(&1), Some(&50));
+    assert_eq!(tree.get(&5), Some(&10));
+
+    assert!(tree.get_pair_mut(&4, &4).is_none());
+    assert!(tree.get_pair_mut(&4, &9).is_none());
+    assert!(tree.get_pair_mut(&0, &4).is_none());
+}
+
+#[test]
+fn test_clear_keep_capacity_drops_entries_once() {
+    use std::{cell::Cell, rc::Rc};
+
+    struct DropCounter(Rc<Cell<usize>>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    let drops = Rc::new(Cell::new(0));
+    let mut tree = RBTree::new();
+    for i in 0..50 {
+        tree.insert(i, DropCounter(Rc::clone(&drops)));
+    }
+
+    tree.clear_keep_capacity();
+    assert_eq!(drops.get(), 50);
+    assert_eq!(tree.capacity(), 50);
+    assert_eq!(tree.iter().count(), 0);
+
+    for i in 0..20 {
+        tree.insert(i, DropCounter(Rc::clone(&drops)));
+    }
+    assert_eq!(tree.capacity(), 50);
+    drop(tree);
+    assert_eq!(drops.get(), 70);
+}
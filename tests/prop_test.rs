@@ -1,5 +1,6 @@
 use proptest::prelude::*;
 use rb_tree::RBTree;
+use std::collections::BTreeMap;
 
 proptest! {
     #[test]
@@ -23,11 +24,43 @@ proptest! {
 
         for (index, key) in unique_keys.iter().enumerate() {
             tree.remove(key);
-            if index % 100 == 0 {
-                if let Err(e) = tree.validate() {
-                    panic!("Tree invalid after removing {}: {}", key, e);
-                }
+            if index % 100 == 0
+                && let Err(e) = tree.validate_dbg()
+            {
+                panic!("Tree invalid after removing {}: {}", key, e);
             }
         }
     }
+
+    #[test]
+    fn retain_matches_btree_map(
+        keys in prop::collection::vec(any::<u16>(), 0..=1000),
+        seed in any::<u32>(),
+        modulus in 1..8u32,
+        threshold in 0..8u32,
+    ) {
+        // an arbitrary but deterministic predicate, so removals are scattered across the tree
+        let keep = |k: &u16| (*k as u32).wrapping_mul(seed | 1) % modulus < threshold;
+
+        let mut tree = RBTree::new();
+        let mut reference = BTreeMap::new();
+        for key in &keys {
+            tree.insert(*key, *key as u32);
+            reference.insert(*key, *key as u32);
+        }
+
+        tree.retain(|k, v| {
+            *v += 1;
+            keep(k)
+        });
+        reference.retain(|k, _| keep(k));
+
+        if let Err(e) = tree.validate() {
+            panic!("Tree invalid after retain: {}", e);
+        }
+        assert_eq!(tree.len(), reference.len());
+        let survivors: Vec<_> = tree.iter().map(|(k, v)| (*k, *v)).collect();
+        let expected: Vec<_> = reference.iter().map(|(k, v)| (*k, *v + 1)).collect();
+        assert_eq!(survivors, expected);
+    }
 }